@@ -1,9 +1,12 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
-use clap::Parser;
-use clap_verbosity_flag::{InfoLevel, Verbosity};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum, ValueHint};
+use log::LevelFilter;
 
-use crate::config::{ColorMode, DisplayMode};
+use crate::completions::Shell;
+use gfld::config::{ColorMode, DisplayMode, EmailScope, GlyphSet, SizeScope, SortBy};
+use gfld::status::Status;
 
 const HELP: &str = "\
 Description: this application helps you keep track of multiple Git repositories via CLI. By default, it displays relevant information for all repos in the current working directory.
@@ -12,27 +15,293 @@ Config file usage: while CLI options are prioritized, default options will fallb
 
     $XDG_CONFIG_HOME/gfld.toml
     $XDG_CONFIG_HOME/gfld/config.toml
-    $HOME/.config/gfld.toml (or {{FOLDERID_Profile}}\\.config\\gfld.toml on Windows)";
+    $HOME/.config/gfld.toml (or {{FOLDERID_Profile}}\\.config\\gfld.toml on Windows)
+
+JSON config files (i.e. with the \"json\" extension instead) are also supported, but TOML config files take precedence in the same location. Alternatively, an explicit config file can be loaded with \"--config\".";
 
 #[derive(Debug, Parser)]
 #[command(version, about = HELP, long_about = None)]
-pub struct Cli {
-    /// Specify path(s) to target directories (defaults to current working directory)
+pub(crate) struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Specify path(s) to target directories, which replace the "paths" from the config file (defaults to those paths or the current working directory); paths with glob metacharacters (e.g. "~/src/*/repo") are expanded to the directories that match
+    #[arg(value_hint = ValueHint::DirPath)]
     pub paths: Option<Vec<PathBuf>>,
-    /// Configure the color settings
+    /// Read newline-delimited paths to target directories from stdin, in addition to the positional paths (paths to ".git" directories are replaced by their parents)
+    #[arg(long)]
+    pub stdin: bool,
+    /// Collect results from the paths of a named group in the config file instead (a header with the group name is displayed in standard display modes)
+    #[arg(short, long, conflicts_with_all = ["paths", "stdin"])]
+    pub group: Option<String>,
+    /// Configure the color settings (takes precedence over the NO_COLOR environment variable, which disables colors when set)
     #[arg(short, long)]
     pub color_mode: Option<ColorMode>,
+    /// Configure the glyphs used for indicators such as statuses in the compact display mode and ahead and behind counts ("ascii" only uses plain characters like "*", "+" and "?")
+    #[arg(long, value_name = "SET")]
+    pub glyphs: Option<GlyphSet>,
+    /// Never display colors (shorthand for "--color-mode never")
+    #[arg(long, conflicts_with = "color_mode")]
+    pub no_color: bool,
     /// Configure how collected information is displayed
     #[arg(short, long)]
     pub display_mode: Option<DisplayMode>,
-    /// Display finalized config options and exit (merged options from an optional config file and command line arguments)
+    /// Truncate lines in the standard display modes to the given width (defaults to the terminal width when displaying to a terminal)
+    #[arg(long, value_name = "COLUMNS")]
+    pub max_width: Option<usize>,
+    /// Print each repository on one line using a template instead of the display mode (placeholders: {name}, {path}, {parent}, {branch}, {status}, {url}, {email}, {ahead}, {behind}, {stashes} and {upstream}; use "{{" and "}}" for literal braces)
+    #[arg(long, value_name = "TEMPLATE")]
+    pub format: Option<String>,
+    /// Sort results by the given field (defaults to the order of the display mode)
+    #[arg(long)]
+    pub sort_by: Option<SortBy>,
+    /// Reverse the order of results
+    #[arg(long)]
+    pub reverse: bool,
+    /// Do not print a summary of the statuses after the results in the standard and classic display modes
+    #[arg(long)]
+    pub no_summary: bool,
+    /// Display the full path of each repository instead of its name in the standard, classic and compact display modes (e.g. to tell apart repositories with the same name under different parents)
     #[arg(long)]
-    pub dry_run: bool,
+    pub absolute_paths: bool,
+    /// Display finalized config options and exit (merged options from an optional config file and command line arguments), or display the repositories that would be collected with "--dry-run=paths"
+    #[arg(long, value_name = "WHAT", num_args = 0..=1, require_equals = true, default_missing_value = "config")]
+    pub dry_run: Option<DryRun>,
+    /// Fetch the current branch from the remote before determining status (HTTPS remotes authenticate with $GFOLD_HTTPS_TOKEN or $GITHUB_TOKEN, if set, and SSH remotes only authenticate with the key file at $GFOLD_SSH_KEY, if set, as the user in $GFOLD_SSH_USER, if set)
     #[arg(long)]
     pub remote: bool,
+    /// Fetch every remote rather than just "origin" (failures are tolerated for each remote)
+    #[arg(long, requires = "remote")]
+    pub fetch_all_remotes: bool,
+    /// Update "origin/HEAD" to the default branch advertised by the remote when fetching "origin" (e.g. after it was renamed from "master" to "main") and list the repositories where it changed on stderr (all branches of "origin" are fetched)
+    #[arg(long, requires = "remote")]
+    pub refresh_origin_head: bool,
+    /// Only fetch repositories matching a glob pattern, with the same syntax as "--exclude" (can be repeated; other repositories still display their local status)
+    #[arg(long, value_name = "PATTERN", requires = "remote")]
+    pub fetch_only: Vec<String>,
+    /// Skip fetching for repositories with local changes (i.e. their status is "unclean" or "conflicted"), which speeds up runs on active working trees (note: ahead and behind counts may be stale for them)
+    #[arg(long, requires = "remote")]
+    pub no_fetch_on_dirty: bool,
+    /// Count the remote-tracking branches that would be pruned after fetching (i.e. those that no longer exist on the remote) without pruning them
+    #[arg(long, requires = "remote")]
+    pub prune_dry_run: bool,
+    /// Only fetch remotes with "https://" URLs (other repositories still display their local status)
+    #[arg(long, requires = "remote", conflicts_with = "remote_only_ssh")]
+    pub remote_only_https: bool,
+    /// Only fetch remotes without "https://" URLs, which are fetched over SSH (other repositories still display their local status)
+    #[arg(long, requires = "remote")]
+    pub remote_only_ssh: bool,
+    /// Only display repositories that need attention (i.e. their status is not "clean")
+    #[arg(long)]
+    pub dirty_only: bool,
+    /// Only display repositories whose status is at least as severe as the given status (clean < unclean < unpushed < unpulled < diverged < conflicted < bare < unknown)
+    #[arg(long, value_name = "STATUS")]
+    pub min_status: Option<Status>,
+    /// Only display repositories whose branch is not a primary branch (see "primary_branches" in the config file, which defaults to "main" and "master"), including those with a detached HEAD
+    #[arg(long)]
+    pub off_branch: bool,
+    /// Only display repositories without a remote (i.e. those that have never been pushed anywhere)
+    #[arg(long)]
+    pub local_only: bool,
+    /// Only display repositories with work that is not on the remote: uncommitted changes (an "unclean" or "conflicted" status), unpushed commits (an "unpushed" or "diverged" status, or commits ahead of the upstream) or at least one stash
+    #[arg(long)]
+    pub only_with_changes_to_push: bool,
+    /// Configure which Git config scope the email is read from
+    #[arg(long)]
+    pub email_scope: Option<EmailScope>,
+    /// Flag repositories whose effective email does not match the regular expression (e.g. "@work\.example\.com$")
+    #[arg(long, value_name = "REGEX")]
+    pub email_allowlist: Option<String>,
+    /// Only display repositories whose last commit is newer than the given duration (e.g. "24h", "7d" or "2w")
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub since: Option<Duration>,
+    /// Only display repositories whose last commit is older than the given duration (e.g. "30d")
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub stale: Option<Duration>,
+    /// Use the git binary to recover the branch and status of repositories that cannot be opened otherwise (e.g. when using "extensions.worktreeConfig")
+    #[arg(long)]
+    pub allow_git_shell: bool,
+    /// Only count commits on the first-parent line for ahead and behind counts like "git log --first-parent" (by default, commits merged in from other branches are counted too), which does not affect statuses
+    #[arg(long)]
+    pub first_parent: bool,
+    /// Skip bare repositories
+    #[arg(long)]
+    pub skip_bare: bool,
+    /// Descend into symlinked directories, which are skipped by default (directories reached more than once are only searched once, which prevents loops)
+    #[arg(long)]
+    pub follow_symlinks: bool,
+    /// Descend into hidden directories (i.e. those whose names start with a dot, such as "~/.dotfiles"), which are skipped by default (".git" directories are always skipped)
+    #[arg(long)]
+    pub include_hidden: bool,
+    /// List Git bundle files (i.e. files with the "bundle" extension) and the refs they contain alongside repositories (only in the standard and classic display modes)
+    #[arg(long)]
+    pub include_bundles: bool,
+    /// List each initialized submodule (recursively) as a repository of its own with its own status and path, instead of nesting it under its superproject (which is marked with "superproject")
+    #[arg(long)]
+    pub flatten_submodules: bool,
+    /// Compute the on-disk size of each repository's Git directory (or of the entire working tree with "--include-size=worktree"), which is displayed in the verbose and JSON display modes
+    #[arg(long, value_name = "SCOPE", num_args = 0..=1, require_equals = true, default_missing_value = "git")]
+    pub include_size: Option<SizeScope>,
+    /// Exclude directories (and everything below them) matching a glob pattern, in addition to patterns from the config file (can be repeated; patterns without a "/" match directory names, others match full paths, e.g. "**/node_modules/**")
+    #[arg(short, long, value_name = "PATTERN")]
+    pub exclude: Vec<String>,
+    /// Limit how many directory levels below each path are searched for repositories (0 only considers the paths themselves; depths for specific paths from "path_depths" in the config file take precedence)
+    #[arg(long, value_name = "N")]
+    pub depth: Option<usize>,
+    /// Abandon a remote fetch if it does not complete within the given number of seconds
+    #[arg(long, value_name = "SECONDS")]
+    pub fetch_timeout: Option<u64>,
+    /// Retry a remote fetch up to the given number of times with exponential backoff if it fails due to a network error (authentication failures are not retried)
+    #[arg(long, value_name = "N")]
+    pub fetch_retries: Option<usize>,
+    /// Cap the number of remote fetches that run at the same time, independent of --jobs (defaults to no limit beyond the number of fetch threads)
+    #[arg(long, value_name = "N", requires = "remote", value_parser = clap::value_parser!(u64).range(1..))]
+    pub fetch_concurrency: Option<u64>,
+    /// Skip fetching a remote if it was fetched within the given number of seconds (tracked in $XDG_CACHE_HOME/gfold)
+    #[arg(long, value_name = "SECONDS")]
+    pub fetch_cache_ttl: Option<u64>,
+    /// Reuse the status of repositories whose HEAD, index and remote-tracking branches have not changed since the last run (tracked in $XDG_CACHE_HOME/gfold; unstaged changes to files made since then are not detected)
+    #[arg(long)]
+    pub status_cache: bool,
+    /// Re-run collection and display every given number of seconds until interrupted (e.g. with Ctrl-C)
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub watch: Option<u64>,
+    /// Browse the results in an interactive list that can be searched by name and filtered by status, with keys to open a shell in a repository or copy its URL (falls back to the normal output when not attached to a terminal)
+    #[arg(long, conflicts_with_all = ["watch", "output", "dry_run"])]
+    pub tui: bool,
+    /// Display only the remote URL of each repository (one per line, sorted and without duplicates) instead of the results, skipping repositories without a remote (e.g. for a script that clones them on a new machine)
+    #[arg(long, conflicts_with_all = ["watch", "output", "dry_run", "tui"])]
+    pub copy_urls: bool,
+    /// Convert the URLs displayed by "--copy-urls" to the given scheme (URLs that are not HTTPS or SSH, such as local paths, are left as-is)
+    #[arg(long, value_name = "SCHEME", requires = "copy_urls")]
+    pub url_scheme: Option<UrlScheme>,
+    /// Collect results (and fetch, if requested) without displaying them, except for the summary of statuses (e.g. for profiling collection or for smoke tests that only check the exit code)
+    #[arg(long)]
+    pub no_display: bool,
+    /// Terminate records with NUL bytes instead of newlines in the porcelain display mode, with "--format" and with "--dry-run=paths" (like "find -print0"; fields are still separated by tabs)
+    #[arg(long)]
+    pub null: bool,
+    /// Write the results to the given file (created or truncated) instead of stdout, while logs are still written to stderr
+    #[arg(short, long, value_name = "PATH", conflicts_with = "watch")]
+    pub output: Option<PathBuf>,
+    /// Cap the number of threads used to collect results (defaults to the number of logical CPUs)
+    #[arg(short, long)]
+    pub jobs: Option<usize>,
+    /// Display the slowest repositories to collect (with the time spent fetching separately) on stderr after the results
+    #[arg(long)]
+    pub profile: bool,
+    /// Number of threads used for remote fetches, which are bound by the network rather than the CPU and are therefore sized separately from --jobs (defaults to 16)
+    #[arg(long, value_name = "N", requires = "remote", value_parser = clap::value_parser!(u64).range(1..))]
+    pub threads_fetch: Option<u64>,
+    /// Exit with 1 if any repository needs attention (i.e. its status is not "clean"), 0 if none do and 2 on errors
+    #[arg(long)]
+    pub exit_code: bool,
+    /// Load the config file at the given path instead of looking in the default locations
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_hint = ValueHint::FilePath,
+        conflicts_with = "ignore_config_file"
+    )]
+    pub config: Option<PathBuf>,
     /// Ignore config file settings
     #[arg(short, long)]
     pub ignore_config_file: bool,
+    /// Write logs (at least up to the debug level) to the given file instead of stderr, with the repository being collected in each line
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+    pub log_file: Option<PathBuf>,
     #[command(flatten)]
-    pub verbose: Verbosity<InfoLevel>,
+    pub verbose: Verbosity,
+}
+
+/// Flags for the logging verbosity (logs are written to stderr). By default, logs up to the "info"
+/// level are written.
+#[derive(Debug, Args)]
+pub(crate) struct Verbosity {
+    /// Increase logging verbosity (can be repeated)
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+    /// Only log errors (repeat to disable logging entirely); results are still printed to stdout
+    #[arg(
+        short,
+        long,
+        action = ArgAction::Count,
+        global = true,
+        conflicts_with = "verbose"
+    )]
+    quiet: u8,
+}
+
+impl Verbosity {
+    /// Find the level that logs are filtered to.
+    pub(crate) fn log_level_filter(&self) -> LevelFilter {
+        match (self.quiet, self.verbose) {
+            (0, 0) => LevelFilter::Info,
+            (0, 1) => LevelFilter::Debug,
+            (0, _) => LevelFilter::Trace,
+            (1, _) => LevelFilter::Error,
+            (_, _) => LevelFilter::Off,
+        }
+    }
+}
+
+/// Subcommands that are run instead of collecting results.
+#[derive(Debug, Subcommand)]
+pub(crate) enum Command {
+    /// Print a completion script for the given shell to stdout
+    Completions {
+        /// The shell to generate the completion script for
+        shell: Shell,
+    },
+    /// Print a man page to stdout (or write it to a directory)
+    Man {
+        /// Write the man page to "<name>.1" in the given directory instead
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+        output_dir: Option<PathBuf>,
+    },
+}
+
+/// What is displayed instead of collecting results when doing a dry run.
+#[remain::sorted]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum DryRun {
+    /// Display the finalized config options.
+    Config,
+    /// Display the paths to the repositories that would be collected without opening them.
+    Paths,
+}
+
+/// The scheme that remote URLs are converted to (see [`convert()`](crate::urls::convert)).
+#[remain::sorted]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum UrlScheme {
+    /// URLs in the form of "https://github.com/owner/repo.git".
+    Https,
+    /// URLs in the form of "git@github.com:owner/repo.git".
+    Ssh,
+}
+
+/// Parse a human-readable duration, which is a whole number followed by a unit: "s" (seconds), "m"
+/// (minutes), "h" (hours), "d" (days) or "w" (weeks).
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let split = value.len() - value.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("expected a number followed by a unit (e.g. \"7d\"): {value}"))?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "expected a unit of \"s\", \"m\", \"h\", \"d\" or \"w\": {value}"
+            ));
+        }
+    };
+    amount
+        .checked_mul(unit_secs)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration is too large: {value}"))
 }
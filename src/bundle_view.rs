@@ -0,0 +1,128 @@
+//! This module contains [`BundleView`], which is the equivalent of
+//! [`RepositoryView`](crate::repository_view::RepositoryView) for Git bundle files.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use log::{debug, trace};
+use serde::{Deserialize, Serialize};
+
+/// A collection of results for a Git bundle file (i.e. a file created by `git bundle create`).
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BundleView {
+    /// The file name of the bundle.
+    pub name: String,
+    /// The parent directory of the bundle. The value will be `None` if a parent is not found.
+    pub parent: Option<String>,
+    /// The refs contained in the bundle. The value will be `None` if the bundle could not be read
+    /// (i.e. its status is unknown).
+    pub heads: Option<Vec<BundleHead>>,
+}
+
+/// A ref contained in a bundle (as listed by `git bundle list-heads`).
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BundleHead {
+    /// The full name of the ref (e.g. "refs/heads/main").
+    pub name: String,
+    /// The object ID that the ref points to.
+    pub oid: String,
+}
+
+impl BundleView {
+    /// Generate a view for the bundle at the given path. Bundles that cannot be read do not
+    /// result in an error, but their heads are unknown.
+    pub fn new(path: &Path) -> Result<Self> {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or(anyhow!("could not convert file name to &str: {path:?}"))?
+            .to_string();
+        let parent = path
+            .parent()
+            .map(|parent| {
+                parent
+                    .to_str()
+                    .map(str::to_string)
+                    .ok_or(anyhow!("could not convert path (Path) to &str: {parent:?}"))
+            })
+            .transpose()?;
+        let heads = match File::open(path).and_then(|file| Self::read_heads(BufReader::new(file))) {
+            Ok(heads) => Some(heads),
+            Err(e) => {
+                debug!("could not read bundle ({e}): {}", path.display());
+                None
+            }
+        };
+        Ok(Self {
+            name,
+            parent,
+            heads,
+        })
+    }
+
+    /// Read the heads from the bundle header, which ends at the first empty line (the packfile
+    /// follows). Both version 2 and version 3 bundles are supported.
+    fn read_heads(mut reader: impl BufRead) -> io::Result<Vec<BundleHead>> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut line = Vec::new();
+        reader.read_until(b'\n', &mut line)?;
+        match line.as_slice() {
+            b"# v2 git bundle\n" | b"# v3 git bundle\n" => {}
+            _ => return Err(invalid("missing bundle signature")),
+        }
+
+        let mut heads = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                return Err(invalid("unexpected end of bundle header"));
+            }
+            let line = std::str::from_utf8(&line)
+                .map_err(|_| invalid("bundle header is not valid UTF-8"))?
+                .trim_end_matches('\n');
+            if line.is_empty() {
+                return Ok(heads);
+            }
+            // Capabilities ("@") and prerequisites ("-") are not heads.
+            if line.starts_with(['@', '-']) {
+                trace!("skipped bundle header line: {line}");
+                continue;
+            }
+            let (oid, name) = line
+                .split_once(' ')
+                .ok_or_else(|| invalid("malformed ref in bundle header"))?;
+            heads.push(BundleHead {
+                name: name.to_string(),
+                oid: oid.to_string(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[allow(clippy::panic_in_result_fn)]
+    #[test]
+    fn read_bundle_heads() -> io::Result<()> {
+        let header: &[u8] = b"# v3 git bundle\n@object-format=sha1\n-0123456 prerequisite\n89abcde refs/heads/main\nfedcba9 refs/tags/v1\n\nPACK\x00\x01";
+        let heads = BundleView::read_heads(header)?;
+        assert_eq!(
+            heads
+                .iter()
+                .map(|head| (head.oid.as_str(), head.name.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("89abcde", "refs/heads/main"), ("fedcba9", "refs/tags/v1")]
+        );
+
+        assert!(BundleView::read_heads(&b"PACK\x00\x01"[..]).is_err());
+        assert!(
+            BundleView::read_heads(&b"# v2 git bundle\n89abcde refs/heads/main\n"[..]).is_err()
+        );
+        Ok(())
+    }
+}
@@ -1,14 +1,33 @@
 //! This module contains the functionality for generating reports.
 
-use std::collections::BTreeMap;
-use std::path::Path;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
 
 use anyhow::Result;
+use log::{debug, warn};
+use progress::Progress;
+use rayon::ThreadPool;
 use rayon::prelude::*;
 use target::TargetCollector;
 
-use crate::repository_view::RepositoryView;
+use crate::bundle_view::BundleView;
+use crate::config::{EmailScope, SizeScope};
+use crate::glob::Glob;
+use crate::regex::Regex;
+use crate::repository_view::error_log::ErrorLog;
+use crate::repository_view::fetch_cache::FetchCache;
+use crate::repository_view::fetch_limiter::FetchLimiter;
+use crate::repository_view::origin_head::OriginHeadLog;
+use crate::repository_view::passphrase::PassphrasePrompt;
+use crate::repository_view::profile::Profile;
+use crate::repository_view::status_cache::StatusCache;
+use crate::repository_view::{LocalView, RepositoryView};
+use crate::status::Status;
 
+mod ignore_file;
+mod progress;
 mod target;
 
 /// This type represents a [`BTreeMap`] using an optional [`String`] for keys, which represents the
@@ -19,34 +38,468 @@ mod target;
 /// sorted keys.
 pub type RepositoryCollection = BTreeMap<Option<String>, Vec<RepositoryView>>;
 
-type UnprocessedRepositoryView = Result<RepositoryView>;
+type UnprocessedRepositoryView = Result<Option<RepositoryView>>;
+
+/// The first stage of collection for a repository (see [`LocalView`]).
+type UnprocessedLocalView = Result<Option<LocalView>>;
+
+/// The transport that remotes are fetched over.
+#[remain::sorted]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    /// Remotes with "https://" URLs.
+    Https,
+    /// All other remotes, which are fetched with SSH credentials.
+    Ssh,
+}
+
+/// Options that dictate what is collected for each [`RepositoryView`].
+#[derive(Debug, Default)]
+pub struct CollectOptions {
+    /// The max number of directory levels below the target path to search (unlimited if `None`).
+    pub max_depth: Option<usize>,
+    /// The max depth for specific target paths, which takes precedence over `max_depth`.
+    pub path_depths: BTreeMap<PathBuf, usize>,
+    /// Prune directories matching any of these patterns (and their descendants) from traversal.
+    pub exclude: Vec<Glob>,
+    /// Include the email used in either the local or global config for the repository.
+    pub include_email: bool,
+    /// The Git config scope that the email is read from.
+    pub email_scope: EmailScope,
+    /// Check if the effective email matches this pattern (no check if `None`).
+    pub email_allowlist: Option<EmailAllowlist>,
+    /// Include views of submodules found within the repository.
+    pub include_submodules: bool,
+    /// Collect initialized submodules (recursively) as repositories of their own rather than as
+    /// views of submodules within their superprojects, which are marked as such.
+    pub flatten_submodules: bool,
+    /// Include the number of modified, untracked and staged files.
+    pub include_file_counts: bool,
+    /// Only count commits on the first-parent line when finding how many commits the current
+    /// branch is ahead and behind its upstream.
+    pub first_parent: bool,
+    /// Include the most recent tag reachable from HEAD.
+    pub include_describe: bool,
+    /// Fall back to the `git` binary for repositories that `libgit2` cannot open (e.g. those using
+    /// the "worktreeconfig" extension).
+    pub allow_git_shell: bool,
+    /// Skip bare repositories entirely.
+    pub skip_bare: bool,
+    /// Collect views of bundle files as well.
+    pub include_bundles: bool,
+    /// Compute the on-disk size of each repository for the given scope (never computed if `None`).
+    pub include_size: Option<SizeScope>,
+    /// Descend into symlinked directories rather than skipping them.
+    pub follow_symlinks: bool,
+    /// Descend into hidden directories (i.e. those whose names start with a dot, except for ".git")
+    /// rather than skipping them.
+    pub include_hidden: bool,
+    /// Fetch the current branch from the remote.
+    pub fetch_remote: bool,
+    /// Skip fetching for repositories with local changes (i.e. their status is "unclean" or
+    /// "conflicted").
+    pub skip_fetch_if_unclean: bool,
+    /// Count the remote-tracking branches that no longer exist on the fetched remotes.
+    pub find_stale_branches: bool,
+    /// Only fetch remotes that use the given transport (all remotes are fetched if `None`).
+    pub fetch_transport: Option<Transport>,
+    /// Fetch every remote rather than just "origin" (or the first remote found).
+    pub fetch_all_remotes: bool,
+    /// Refresh "origin/HEAD" from the remote whenever "origin" is fetched and record the
+    /// repositories where it changed (never refreshed if `None`).
+    pub origin_head_log: Option<OriginHeadLog>,
+    /// The names of remotes in order of preference, which decides the remote that is displayed,
+    /// fetched and compared against ("origin" is preferred if empty). A remote is greedily chosen
+    /// if none of them are found.
+    pub remote_preference: Vec<String>,
+    /// Only fetch repositories whose paths match any of these patterns (all repositories are
+    /// fetched if empty).
+    pub fetch_only: Vec<Glob>,
+    /// The passphrase for the SSH key used when fetching (empty if the key is not encrypted).
+    pub fetch_password: String,
+    /// Prompt for the SSH key passphrase if authenticating with a key fails (never prompt if
+    /// `None`).
+    pub passphrase_prompt: Option<PassphrasePrompt>,
+    /// Abandon a fetch if it does not complete within this duration (no limit if `None`).
+    pub fetch_timeout: Option<Duration>,
+    /// Retry a fetch up to this many times if it fails due to a transient error (e.g. a network
+    /// error).
+    pub fetch_retries: usize,
+    /// Limit the number of remote fetches that run at the same time (no limit if `None`).
+    pub fetch_limiter: Option<Arc<FetchLimiter>>,
+    /// Run remote fetches on this thread pool, which is sized separately from the collection
+    /// thread pool since fetches are bound by the network rather than the CPU (fetches run on the
+    /// collection thread pool if `None`). Fetches run once local state has been collected for
+    /// every repository (see [`LocalView`]).
+    pub fetch_pool: Option<Arc<ThreadPool>>,
+    /// Skip fetching remotes that were fetched recently (always fetch if `None`).
+    pub fetch_cache: Option<FetchCache>,
+    /// Reuse the statuses of repositories that have not changed since the last run (always find
+    /// the status if `None`).
+    pub status_cache: Option<StatusCache>,
+    /// Record how long each repository took to collect (never recorded if `None`).
+    pub profile: Option<Profile>,
+    /// Record errors for individual repositories (i.e. those that cannot be opened, collected or
+    /// fetched) and continue collecting rather than failing the entire collection (fetch failures
+    /// are only logged if `None`).
+    pub error_log: Option<ErrorLog>,
+    /// Record the directories that were skipped during traversal because they could not be read
+    /// due to permissions (never recorded if `None`). They are recorded in the error log as well.
+    pub skipped_directories: Option<SkippedDirectories>,
+    /// Display how many repositories have been processed on `stderr` while collecting.
+    pub show_progress: bool,
+    /// Only include views whose status is not "clean".
+    pub dirty_only: bool,
+    /// Only include views whose status is at least as severe as this status (all views are
+    /// included if `None`).
+    pub min_status: Option<Status>,
+    /// Only include views whose branch is not one of these primary branches (all views are
+    /// included if `None`). Views with a detached HEAD are never on a primary branch.
+    pub off_branch: Option<Vec<String>>,
+    /// Only include views without a remote (i.e. their url is `None`).
+    pub local_only: bool,
+    /// Only include views with work that is not on the remote: uncommitted changes (i.e. their
+    /// status is "unclean" or "conflicted"), unpushed commits (i.e. their status is "unpushed" or
+    /// "diverged" or they are ahead of their upstream) or at least one stash.
+    pub only_with_changes_to_push: bool,
+    /// Only include views whose last commit is newer than this time (in seconds since the Unix
+    /// epoch).
+    pub committed_after: Option<i64>,
+    /// Only include views whose last commit is older than this time (in seconds since the Unix
+    /// epoch).
+    pub committed_before: Option<i64>,
+}
+
+impl CollectOptions {
+    /// Find the max depth for a target path, preferring the depth specific to the path (if any).
+    fn max_depth_for(&self, path: &Path) -> Option<usize> {
+        self.path_depths.get(path).copied().or(self.max_depth)
+    }
+
+    /// Record the error for the repository (or bundle) at the given path in the error log, if any,
+    /// in which case the value will be `None`. Otherwise, the error is returned.
+    fn tolerate<T>(&self, path: &Path, result: Result<T>) -> Result<Option<T>> {
+        match (result, &self.error_log) {
+            (Ok(value), _) => Ok(Some(value)),
+            (Err(e), Some(error_log)) => {
+                warn!(
+                    "could not collect {} (recorded error: {e:#})",
+                    path.display()
+                );
+                error_log.record(path, format!("{e:#}"));
+                Ok(None)
+            }
+            (Err(e), None) => Err(e),
+        }
+    }
+
+    /// Run the operation on the fetch thread pool (if any), which runs the second stage of
+    /// collection (see [`LocalView`]). Otherwise, it runs on the current thread pool.
+    fn on_fetch_pool<R: Send>(&self, operation: impl FnOnce() -> R + Send) -> R {
+        match &self.fetch_pool {
+            Some(fetch_pool) => fetch_pool.install(operation),
+            None => operation(),
+        }
+    }
+
+    /// Check if a collected [`RepositoryView`] passes the filters that were provided. Views without
+    /// a last commit time never pass the commit time filters.
+    fn includes(&self, view: &RepositoryView) -> bool {
+        if self.dirty_only && view.status == Status::Clean {
+            return false;
+        }
+        if let Some(min_status) = self.min_status
+            && !view.status.is_at_least(min_status)
+        {
+            return false;
+        }
+        if let Some(primary_branches) = &self.off_branch
+            && primary_branches.contains(&view.branch)
+        {
+            return false;
+        }
+        if self.local_only && view.url.is_some() {
+            return false;
+        }
+        // Uncommitted changes take precedence over unpushed commits in the status, so the ahead
+        // count is checked as well.
+        if self.only_with_changes_to_push
+            && !matches!(
+                view.status,
+                Status::Unclean | Status::Conflicted | Status::Unpushed | Status::Diverged
+            )
+            && view.ahead == 0
+            && view.stashes == 0
+        {
+            return false;
+        }
+        if let Some(committed_after) = self.committed_after
+            && view
+                .last_commit_time
+                .is_none_or(|time| time <= committed_after)
+        {
+            return false;
+        }
+        if let Some(committed_before) = self.committed_before
+            && view
+                .last_commit_time
+                .is_none_or(|time| time >= committed_before)
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// A pattern that the effective email of each repository is checked against. Patterns are regular
+/// expressions supporting a common subset of the syntax (i.e. classes, groups, alternation,
+/// quantifiers, anchors and a leading `(?i)` for case-insensitivity), which match anywhere in the
+/// email unless anchored.
+#[derive(Debug, Clone)]
+pub struct EmailAllowlist(Regex);
+
+impl EmailAllowlist {
+    /// Compile the pattern. An error is returned if the pattern is malformed.
+    pub fn new(pattern: &str) -> Result<Self> {
+        Ok(Self(Regex::new(pattern)?))
+    }
+
+    /// Return the original pattern.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Check if the email is allowed (i.e. the pattern matches anywhere in it).
+    pub fn is_match(&self, email: &str) -> bool {
+        self.0.is_match(email)
+    }
+}
+
+/// The canonical paths of the repositories collected so far, which is used to only collect the
+/// same repository once when it is found more than once (e.g. through overlapping paths like
+/// "~/src" and "~/src/project" or through a symlink and its target). Duplicates are removed from
+/// the targets after traversal, so that they are never opened (or fetched) twice.
+#[derive(Debug, Default)]
+pub struct SeenRepositories {
+    paths: Mutex<HashSet<PathBuf>>,
+}
+
+impl SeenRepositories {
+    /// Record the repository at the path as seen. Returns `false` if it was already seen.
+    /// Repositories that cannot be canonicalized are always considered unseen.
+    fn first_seen(&self, path: &Path) -> bool {
+        let canonical = match path.canonicalize() {
+            Ok(canonical) => canonical,
+            Err(e) => {
+                debug!(
+                    "could not canonicalize (ignored error: {e}): {}",
+                    path.display()
+                );
+                return true;
+            }
+        };
+        let first_seen = self
+            .paths
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(canonical);
+        if !first_seen {
+            debug!(
+                "skipping repository that was already found: {}",
+                path.display()
+            );
+        }
+        first_seen
+    }
+
+    /// Sort the targets and remove those for repositories that were already seen (including
+    /// duplicates within the targets, in which case the first path is kept).
+    fn retain_first_seen(&self, targets: &mut Vec<PathBuf>) {
+        targets.sort();
+        targets.retain(|path| self.first_seen(path));
+    }
+}
+
+/// The directories that were skipped during traversal because they could not be read due to
+/// permissions (e.g. when searching "/" or shared mounts).
+#[derive(Debug, Default)]
+pub struct SkippedDirectories {
+    paths: Mutex<Vec<PathBuf>>,
+}
+
+impl SkippedDirectories {
+    /// Record the directory as skipped.
+    pub fn record(&self, path: &Path) {
+        self.paths
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(path.to_path_buf());
+    }
+
+    /// Take the directories recorded so far, sorted by path. All directories are cleared, so that
+    /// each run (e.g. when watching) only reports its own directories.
+    pub fn take(&self) -> Vec<PathBuf> {
+        let mut paths =
+            std::mem::take(&mut *self.paths.lock().unwrap_or_else(PoisonError::into_inner));
+        paths.sort();
+        paths
+    }
+}
 
 /// A unit struct that provides [`Self::run()`], which is used to generated [`RepositoryCollection`].
 #[derive(Debug)]
 pub struct RepositoryCollector;
 
 impl RepositoryCollector {
-    /// Generate [`RepositoryCollection`] for a given path and its children.
+    /// Generate [`RepositoryCollection`] for a given path and its children. Repositories that were
+    /// already seen (e.g. for a previous path) are skipped. Each group of views is sorted by name.
+    /// If collecting any view fails, the error is recorded in the error log (if any) or the first
+    /// error (in traversal order) is returned. Views of bundles (if included) are returned
+    /// alongside the collection, sorted by path.
     pub fn run(
         path: &Path,
-        include_email: bool,
-        include_submodules: bool,
-        fetch_remote: bool,
-        fetch_password: String,
-    ) -> Result<RepositoryCollection> {
-        let unprocessed = TargetCollector::run(path.to_path_buf())?
+        options: &CollectOptions,
+        seen: &SeenRepositories,
+    ) -> Result<(RepositoryCollection, Vec<BundleView>)> {
+        let mut targets = TargetCollector::run(path.to_path_buf(), options)?;
+        seen.retain_first_seen(&mut targets.repositories);
+        let progress = Progress::new(targets.repositories.len(), options.show_progress);
+        // Local state is collected on the current thread pool first. Then, remotes are fetched on
+        // the fetch thread pool, so that the number of fetches at once does not depend on it.
+        let local = targets
+            .repositories
             .par_iter()
-            .map(|path| RepositoryView::new(path, include_email, include_submodules, fetch_remote, fetch_password.clone()))
-            .collect::<Vec<UnprocessedRepositoryView>>();
+            .map(|path| {
+                let local = RepositoryView::collect_local(path, options);
+                if !Self::needs_fetch(&local) {
+                    progress.increment();
+                }
+                local
+            })
+            .collect::<Vec<UnprocessedLocalView>>();
+        let unprocessed = options.on_fetch_pool(|| {
+            targets
+                .repositories
+                .par_iter()
+                .zip(local)
+                .map(|(path, local)| {
+                    let needs_fetch = Self::needs_fetch(&local);
+                    let view = Self::finish(local, options);
+                    if needs_fetch {
+                        progress.increment();
+                    }
+                    (path, view)
+                })
+                .collect::<Vec<(&PathBuf, UnprocessedRepositoryView)>>()
+        });
+        progress.finish();
 
         let mut processed = RepositoryCollection::new();
-        for maybe_view in unprocessed {
-            let view = maybe_view?;
+        for (path, maybe_view) in unprocessed {
+            let Some(maybe_view) = options.tolerate(path, maybe_view)? else {
+                continue;
+            };
+            let Some(view) = maybe_view.filter(|view| options.includes(view)) else {
+                continue;
+            };
             if let Some(mut views) = processed.insert(view.parent.clone(), vec![view.clone()]) {
                 views.push(view.clone());
                 processed.insert(view.parent, views);
             }
         }
-        Ok(processed)
+
+        // Views are collected in parallel, so we sort each group to ensure deterministic output.
+        for views in processed.values_mut() {
+            views.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+
+        let mut bundles = Vec::new();
+        let unprocessed = targets
+            .bundles
+            .par_iter()
+            .map(|path| (path, BundleView::new(path)))
+            .collect::<Vec<(&PathBuf, Result<BundleView>)>>();
+        for (path, maybe_bundle) in unprocessed {
+            bundles.extend(options.tolerate(path, maybe_bundle)?);
+        }
+        bundles.sort_by(|a, b| (&a.parent, &a.name).cmp(&(&b.parent, &b.name)));
+        Ok((processed, bundles))
+    }
+
+    /// Find the paths to the repositories in a given path and its children without opening them,
+    /// sorted by path. Repositories that were already seen are skipped like for [`Self::run()`].
+    /// Bundles are never included.
+    pub fn targets(
+        path: &Path,
+        options: &CollectOptions,
+        seen: &SeenRepositories,
+    ) -> Result<Vec<PathBuf>> {
+        let mut repositories = TargetCollector::run(path.to_path_buf(), options)?.repositories;
+        seen.retain_first_seen(&mut repositories);
+        Ok(repositories)
+    }
+
+    /// Generate a [`RepositoryView`] for each repository found in a given path and its children,
+    /// handing each view to `on_view` as soon as it has been collected. Errors are handled the same
+    /// way as for [`Self::run()`] (and so are repositories that were already seen), but views are
+    /// not buffered and the order in which they are handed off is not guaranteed. Bundles are never
+    /// collected.
+    pub fn stream<F>(
+        path: &Path,
+        options: &CollectOptions,
+        seen: &SeenRepositories,
+        on_view: F,
+    ) -> Result<()>
+    where
+        F: Fn(RepositoryView) -> Result<()> + Sync,
+    {
+        let mut targets = TargetCollector::run(path.to_path_buf(), options)?;
+        seen.retain_first_seen(&mut targets.repositories);
+        let progress = Progress::new(targets.repositories.len(), options.show_progress);
+        let hand_off = |path: &Path, view: UnprocessedRepositoryView| {
+            progress.increment();
+            match options.tolerate(path, view)?.flatten() {
+                Some(view) if options.includes(&view) => on_view(view),
+                Some(_) => Ok(()),
+                None => Ok(()),
+            }
+        };
+        // Views that need a fetch are handed off once their remotes have been fetched on the fetch
+        // thread pool, after local state has been collected for every repository.
+        let pending = Mutex::new(Vec::new());
+        let result = targets
+            .repositories
+            .par_iter()
+            .try_for_each(|path| match RepositoryView::collect_local(path, options) {
+                Ok(Some(local)) if local.needs_fetch() => {
+                    pending
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push((path, local));
+                    Ok(())
+                }
+                local => hand_off(path, Self::finish(local, options)),
+            })
+            .and_then(|()| {
+                let pending = pending.into_inner().unwrap_or_else(PoisonError::into_inner);
+                options.on_fetch_pool(|| {
+                    pending.into_par_iter().try_for_each(|(path, local)| {
+                        hand_off(path, local.finish(options).map(Some))
+                    })
+                })
+            });
+        progress.finish();
+        result
+    }
+
+    /// Check if the first stage of collection left a fetch for the second stage.
+    fn needs_fetch(local: &UnprocessedLocalView) -> bool {
+        matches!(local, Ok(Some(local)) if local.needs_fetch())
+    }
+
+    /// Run the second stage of collection (see [`LocalView::finish()`]), if the first stage
+    /// succeeded.
+    fn finish(local: UnprocessedLocalView, options: &CollectOptions) -> UnprocessedRepositoryView {
+        local.and_then(|local| local.map(|local| local.finish(options)).transpose())
     }
 }
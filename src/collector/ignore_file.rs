@@ -0,0 +1,118 @@
+//! This module contains [`IgnoreFile`], which prunes directories from traversal based on
+//! `.gfoldignore` files.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::{debug, warn};
+
+use crate::glob::Glob;
+
+/// The name of the file that prunes directories from traversal.
+const FILE_NAME: &str = ".gfoldignore";
+
+/// The patterns from a `.gfoldignore` file. An empty file (or one with only blank lines and
+/// comments starting with `#`) prunes the directory containing it. Otherwise, each line is a glob
+/// pattern (see [`Glob`] for the syntax) matched against the paths of descendant directories
+/// relative to the directory containing the file, and matching directories are pruned.
+#[derive(Debug, Clone)]
+pub(super) struct IgnoreFile {
+    root: PathBuf,
+    globs: Vec<Glob>,
+    prunes_root: bool,
+}
+
+impl IgnoreFile {
+    /// Load the `.gfoldignore` file in the directory, if one exists. Errors when reading the file
+    /// and invalid patterns are logged and ignored.
+    pub(super) fn load(directory: &Path) -> Option<Self> {
+        let path = directory.join(FILE_NAME);
+        match fs::read_to_string(&path) {
+            Ok(contents) => Some(Self::parse(directory, &contents)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            // Directories that cannot be read at all are reported when they are walked instead.
+            Err(e)
+                if e.kind() == io::ErrorKind::PermissionDenied
+                    && fs::read_dir(directory).is_err() =>
+            {
+                debug!("could not read ignore file ({e}): {}", path.display());
+                None
+            }
+            Err(e) => {
+                warn!("could not read ignore file ({e}): {}", path.display());
+                None
+            }
+        }
+    }
+
+    fn parse(directory: &Path, contents: &str) -> Self {
+        let lines: Vec<&str> = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+        let globs = lines
+            .iter()
+            .filter_map(|line| match Glob::new(line) {
+                Ok(glob) => Some(glob),
+                Err(e) => {
+                    warn!(
+                        "ignored invalid pattern in ignore file ({e}): {}",
+                        directory.display()
+                    );
+                    None
+                }
+            })
+            .collect();
+        Self {
+            root: directory.to_path_buf(),
+            globs,
+            // Files with only invalid patterns do not prune the directory.
+            prunes_root: lines.is_empty(),
+        }
+    }
+
+    /// Check if the directory containing the file is pruned entirely.
+    pub(super) fn prunes_root(&self) -> bool {
+        self.prunes_root
+    }
+
+    /// Check if a descendant directory matches any of the patterns.
+    pub(super) fn is_match(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        match self.globs.iter().find(|glob| glob.is_match(relative)) {
+            Some(glob) => {
+                debug!(
+                    "ignored by pattern ({}) in {}: {:?}",
+                    glob.as_str(),
+                    self.root.join(FILE_NAME).display(),
+                    path.display()
+                );
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignore_file_matching() {
+        let root = Path::new("/src");
+        assert!(IgnoreFile::parse(root, "\n# nothing to see here\n").prunes_root());
+
+        let ignore_file = IgnoreFile::parse(root, "vendor\nthird_party/**\n");
+        assert!(!ignore_file.prunes_root());
+        assert!(ignore_file.is_match(Path::new("/src/vendor")));
+        assert!(ignore_file.is_match(Path::new("/src/app/vendor")));
+        assert!(ignore_file.is_match(Path::new("/src/third_party/lib")));
+        assert!(!ignore_file.is_match(Path::new("/src/app/third_party")));
+        assert!(!ignore_file.is_match(Path::new("/other/vendor")));
+    }
+}
@@ -0,0 +1,59 @@
+//! This module contains [`Progress`], which displays how many repositories have been processed.
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Clears the current line and moves the cursor to its start.
+const CLEAR_LINE: &str = "\r\x1b[2K";
+
+/// A single-line progress indicator written to `stderr`, which leaves `stdout` untouched. It does
+/// nothing if disabled.
+#[derive(Debug)]
+pub(crate) struct Progress {
+    enabled: bool,
+    total: usize,
+    processed: AtomicUsize,
+}
+
+impl Progress {
+    /// Create a progress indicator for the given number of repositories.
+    pub(crate) fn new(total: usize, enabled: bool) -> Self {
+        let progress = Self {
+            enabled: enabled && total > 0,
+            total,
+            processed: AtomicUsize::new(0),
+        };
+        progress.update(0);
+        progress
+    }
+
+    /// Mark a repository as processed and update the indicator.
+    pub(crate) fn increment(&self) {
+        self.update(1);
+    }
+
+    /// Clear the indicator, which ensures that it stays out of the way of the final results.
+    pub(crate) fn finish(&self) {
+        if self.enabled {
+            let mut stderr = io::stderr().lock();
+            // Failing to display progress is not worth failing over, so errors are ignored.
+            let _ = write!(stderr, "{CLEAR_LINE}");
+            let _ = stderr.flush();
+        }
+    }
+
+    fn update(&self, increment: usize) {
+        if self.enabled {
+            // The count is updated while holding the lock, which ensures that counts written from
+            // multiple threads are displayed in order.
+            let mut stderr = io::stderr().lock();
+            let processed = self.processed.fetch_add(increment, Ordering::Relaxed) + increment;
+            let _ = write!(
+                stderr,
+                "{CLEAR_LINE}{processed}/{} repos processed",
+                self.total
+            );
+            let _ = stderr.flush();
+        }
+    }
+}
@@ -1,93 +1,369 @@
 //! This module contains target generation logic required for generating
 //! [`RepositoryViews`](crate::repository_view::RepositoryView).
 
+use git2::Repository;
 use log::{debug, error, warn};
 use rayon::prelude::*;
+use std::collections::HashSet;
 use std::fs::DirEntry;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::{fs, io};
 
+use crate::collector::CollectOptions;
+use crate::collector::ignore_file::IgnoreFile;
+use crate::repository_view::RepositoryView;
+
 /// An unprocessed target that needs to be disassembled before consumption.
 type UnprocessedTarget = io::Result<MaybeTarget>;
 
+/// The canonical paths of the directories that have been visited, which is used to prevent
+/// loops when following symlinks.
+type Visited = Mutex<HashSet<PathBuf>>;
+
+/// The state shared by every directory walked for a given path.
+#[derive(Default)]
+struct WalkState {
+    /// The max depth for the given path (see [`CollectOptions::max_depth_for()`]).
+    max_depth: Option<usize>,
+    visited: Visited,
+}
+
+/// The targets found for a given path.
+#[derive(Debug, Default)]
+pub(crate) struct Targets {
+    /// Paths to repositories.
+    pub(crate) repositories: Vec<PathBuf>,
+    /// Paths to bundle files (only found if bundles are included).
+    pub(crate) bundles: Vec<PathBuf>,
+}
+
 /// A unit struct used to centralizing target collection method(s).
 pub(crate) struct TargetCollector;
 
 impl TargetCollector {
     /// Generate targets for a given [`PathBuf`] based on its children (recursively). We use
     /// recursion paired with [`rayon`] since we prioritize speed over memory use.
-    pub(crate) fn run(path: PathBuf) -> io::Result<Vec<PathBuf>> {
+    ///
+    /// If the given path is a repository itself (including the working tree named by
+    /// `$GIT_WORK_TREE`), it is a target as well and its children are still walked (e.g. for a
+    /// dotfiles repository in the home directory), unlike repositories found while walking, which
+    /// are not descended into.
+    ///
+    /// If a max depth is provided (either for the given path or for all paths), only directories
+    /// up to that many levels below the given path are searched. A max depth of zero means that
+    /// only the given path itself is considered. The depth is counted along the directories
+    /// actually walked (including symlinks).
+    ///
+    /// Symlinked directories are skipped unless following symlinks is enabled. When following,
+    /// each directory is only walked once (by canonical path), which prevents symlink loops.
+    ///
+    /// Directories matching any of the exclude patterns are pruned (i.e. neither they nor their
+    /// descendants are considered). Directories are also pruned by `.gfoldignore` files (see
+    /// [`IgnoreFile`]) found in the given path or in any directory walked.
+    ///
+    /// If bundles are included, files with the "bundle" extension found while walking are
+    /// targets as well.
+    pub(crate) fn run(path: PathBuf, options: &CollectOptions) -> io::Result<Targets> {
+        let mut targets = Targets::default();
+        if Self::is_repository(&path) || RepositoryView::is_env_work_tree(&path) {
+            targets.repositories = Self::repository_targets(path.clone(), options);
+        }
+        let state = WalkState {
+            max_depth: options.max_depth_for(&path),
+            ..Default::default()
+        };
+        if state.max_depth == Some(0) {
+            return Ok(targets);
+        }
+        if options.follow_symlinks {
+            Self::visit(&state.visited, &path);
+        }
+        let ignore_files = match IgnoreFile::load(&path) {
+            Some(ignore_file) if ignore_file.prunes_root() => {
+                debug!("ignored by empty ignore file: {:?}", &path.display());
+                return Ok(targets);
+            }
+            Some(ignore_file) => vec![ignore_file],
+            None => Vec::with_capacity(0),
+        };
+        let children = Self::walk(path, 1, options, &state, &ignore_files)?;
+        targets.repositories.extend(children.repositories);
+        targets.bundles = children.bundles;
+        Ok(targets)
+    }
+
+    /// Generate targets for the children of the given [`PathBuf`], which are found at the given
+    /// depth relative to the original path. If the directory cannot be read due to permissions, it
+    /// is skipped and recorded (see [`CollectOptions::skipped_directories`]) rather than failing the
+    /// entire walk.
+    fn walk(
+        path: PathBuf,
+        depth: usize,
+        options: &CollectOptions,
+        state: &WalkState,
+        ignore_files: &[IgnoreFile],
+    ) -> io::Result<Targets> {
         let entries: Vec<DirEntry> = match fs::read_dir(&path) {
             Ok(read_dir) => read_dir.filter_map(|r| r.ok()).collect(),
             Err(e) => {
                 match e.kind() {
-                    io::ErrorKind::PermissionDenied => warn!("{}: {}", e, &path.display()),
+                    io::ErrorKind::PermissionDenied => Self::skip(&path, &e, options),
                     _ => error!("{}: {}", e, &path.display()),
                 }
-                return Ok(Vec::with_capacity(0));
+                return Ok(Targets::default());
             }
         };
 
         let unprocessed = entries
             .par_iter()
-            .map(Self::determine_target)
+            .map(|entry| Self::determine_target(entry, depth, options, state, ignore_files))
             .collect::<Vec<UnprocessedTarget>>();
 
-        let mut results = Vec::new();
+        let mut results = Targets::default();
         for entry in unprocessed {
-            let entry = entry?;
-            if let MaybeTarget::Multiple(targets) = entry {
-                results.extend(targets);
-            } else if let MaybeTarget::Single(target) = entry {
-                results.push(target);
+            match entry? {
+                MaybeTarget::Bundle(target) => results.bundles.push(target),
+                MaybeTarget::Multiple(targets) => {
+                    results.repositories.extend(targets.repositories);
+                    results.bundles.extend(targets.bundles);
+                }
+                MaybeTarget::None => {}
+                MaybeTarget::Single(target) => results.repositories.push(target),
             }
         }
         Ok(results)
     }
 
-    /// Ensure the entry is a directory and is not hidden. Then, check if a ".git" sub directory
-    /// exists, which will indicate if the entry is a repository. If the directory is not a Git
-    /// repository, then we will recursively call [`Self::run()`].
-    fn determine_target(entry: &DirEntry) -> io::Result<MaybeTarget> {
-        if entry.file_type()?.is_dir()
-            && !entry
-                .file_name()
-                .to_str()
-                .is_some_and(|file_name| file_name.starts_with('.'))
-        {
+    /// Skip a directory that cannot be read due to permissions, which is logged and recorded (in
+    /// the skipped directories and the error log, if any).
+    fn skip(path: &Path, error: &io::Error, options: &CollectOptions) {
+        warn!("skipping directory; {error}: {}", path.display());
+        if let Some(skipped_directories) = &options.skipped_directories {
+            skipped_directories.record(path);
+        }
+        if let Some(error_log) = &options.error_log {
+            error_log.record(path, format!("skipped directory: {error}"));
+        }
+    }
+
+    /// Ensure the entry is a directory (or a symlink to one if following symlinks), is not hidden
+    /// (see [`Self::is_hidden()`]), is not excluded or ignored and has not been visited. Then,
+    /// check if the entry is a repository. If the directory is not a Git repository and the max
+    /// depth has not been reached, then we will recursively call [`Self::walk()`]. Files are only
+    /// targets if they are bundles and bundles are included.
+    fn determine_target(
+        entry: &DirEntry,
+        depth: usize,
+        options: &CollectOptions,
+        state: &WalkState,
+        ignore_files: &[IgnoreFile],
+    ) -> io::Result<MaybeTarget> {
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                Self::skip(&entry.path(), &e, options);
+                return Ok(MaybeTarget::None);
+            }
+            Err(e) => return Err(e),
+        };
+        let is_dir = match file_type.is_symlink() {
+            // Broken symlinks are skipped like any other non-directory.
+            true => options.follow_symlinks && entry.path().is_dir(),
+            false => file_type.is_dir(),
+        };
+        if !is_dir && options.include_bundles {
+            return Ok(Self::determine_bundle(entry, options, ignore_files));
+        }
+        if is_dir && !Self::is_hidden(entry, options) {
             let path = entry.path();
-            
-            // Check for bare repository - e.g. if there is file named "HEAD" in the root
-            let bare = path.join("HEAD").exists();
-            if bare {
-                debug!("found bare repository: {:?}", &path);
-                return Ok(MaybeTarget::Single(path));
+            if let Some(glob) = options.exclude.iter().find(|glob| glob.is_match(&path)) {
+                debug!(
+                    "excluded by pattern ({}): {:?}",
+                    glob.as_str(),
+                    &path.display()
+                );
+                return Ok(MaybeTarget::None);
             }
-
-            let git_sub_item = path.join(".git");
-            if git_sub_item.exists() {
-                if git_sub_item.is_dir() {
-                    debug!("found target: {:?}", &path.display());
-                    return Ok(MaybeTarget::Single(path));
-                } else if git_sub_item.is_file() {
-                    debug!("found a worktree: {:?}", &path.display());
-                    return Ok(MaybeTarget::Single(path));
-                }
+            if ignore_files
+                .iter()
+                .any(|ignore_file| ignore_file.is_match(&path))
+            {
+                return Ok(MaybeTarget::None);
+            }
+            let ignore_file = IgnoreFile::load(&path);
+            if ignore_file
+                .as_ref()
+                .is_some_and(|ignore_file| ignore_file.prunes_root())
+            {
+                debug!("ignored by empty ignore file: {:?}", &path.display());
+                return Ok(MaybeTarget::None);
+            }
+            if options.follow_symlinks && !Self::visit(&state.visited, &path) {
+                debug!("already visited: {:?}", &path.display());
+                return Ok(MaybeTarget::None);
+            }
+            if Self::is_repository(&path) {
+                return Ok(match options.flatten_submodules {
+                    true => MaybeTarget::Multiple(Targets {
+                        repositories: Self::repository_targets(path, options),
+                        bundles: Vec::with_capacity(0),
+                    }),
+                    false => MaybeTarget::Single(path),
+                });
             }
-            Ok(MaybeTarget::Multiple(Self::run(path)?))
+            if state.max_depth.is_some_and(|max_depth| depth >= max_depth) {
+                debug!("reached max depth ({depth}): {:?}", &path.display());
+                return Ok(MaybeTarget::None);
+            }
+            // Patterns from ignore files apply to all descendants of the directory containing them.
+            let ignore_files = match ignore_file {
+                Some(ignore_file) => [ignore_files, &[ignore_file]].concat(),
+                None => ignore_files.to_vec(),
+            };
+            Ok(MaybeTarget::Multiple(Self::walk(
+                path,
+                depth + 1,
+                options,
+                state,
+                &ignore_files,
+            )?))
         } else {
             Ok(MaybeTarget::None)
         }
     }
+
+    /// Check if the (non-directory) entry is a bundle that is neither hidden, excluded nor ignored.
+    fn determine_bundle(
+        entry: &DirEntry,
+        options: &CollectOptions,
+        ignore_files: &[IgnoreFile],
+    ) -> MaybeTarget {
+        let path = entry.path();
+        let is_bundle = path
+            .extension()
+            .is_some_and(|extension| extension == "bundle")
+            && !Self::is_hidden(entry, options)
+            && path.is_file();
+        if !is_bundle
+            || options.exclude.iter().any(|glob| glob.is_match(&path))
+            || ignore_files
+                .iter()
+                .any(|ignore_file| ignore_file.is_match(&path))
+        {
+            return MaybeTarget::None;
+        }
+        debug!("found bundle: {:?}", &path.display());
+        MaybeTarget::Bundle(path)
+    }
+
+    /// Check if the entry is hidden (i.e. its name starts with a dot) and hidden entries are not
+    /// included. The ".git" directory is always hidden since its internals are never repositories.
+    fn is_hidden(entry: &DirEntry, options: &CollectOptions) -> bool {
+        let file_name = entry.file_name();
+        let Some(file_name) = file_name.to_str() else {
+            return false;
+        };
+        file_name == ".git" || (file_name.starts_with('.') && !options.include_hidden)
+    }
+
+    /// Record the canonical path of the directory as visited. Returns `false` if it was already
+    /// visited. Directories that cannot be canonicalized are always considered unvisited.
+    fn visit(visited: &Visited, path: &Path) -> bool {
+        let canonical = match path.canonicalize() {
+            Ok(canonical) => canonical,
+            Err(e) => {
+                debug!(
+                    "could not canonicalize (ignored error: {e}): {:?}",
+                    path.display()
+                );
+                return true;
+            }
+        };
+        match visited.lock() {
+            Ok(mut visited) => visited.insert(canonical),
+            Err(_) => true,
+        }
+    }
+
+    /// The targets for a repository, which are the repository itself followed by its initialized
+    /// submodules (recursively) if submodules are flattened. Submodules that cannot be listed are
+    /// skipped.
+    fn repository_targets(path: PathBuf, options: &CollectOptions) -> Vec<PathBuf> {
+        let mut targets = vec![path];
+        if !options.flatten_submodules {
+            return targets;
+        }
+        let submodules = match Repository::open(&targets[0]) {
+            Ok(repo) => match (repo.workdir(), repo.submodules()) {
+                (Some(workdir), Ok(submodules)) => submodules
+                    .iter()
+                    .map(|submodule| workdir.join(submodule.path()))
+                    .collect::<Vec<PathBuf>>(),
+                (_, Err(e)) => {
+                    debug!(
+                        "could not list submodules (ignored error: {e}): {:?}",
+                        &targets[0]
+                    );
+                    Vec::with_capacity(0)
+                }
+                (None, Ok(_)) => Vec::with_capacity(0),
+            },
+            Err(e) => {
+                debug!(
+                    "could not open repository (ignored error: {e}): {:?}",
+                    &targets[0]
+                );
+                Vec::with_capacity(0)
+            }
+        };
+        for submodule in submodules {
+            if Self::is_repository(&submodule) {
+                debug!("found submodule: {:?}", &submodule.display());
+                targets.extend(Self::repository_targets(submodule, options));
+            } else {
+                debug!(
+                    "skipping uninitialized submodule: {:?}",
+                    &submodule.display()
+                );
+            }
+        }
+        targets
+    }
+
+    /// Check if a ".git" sub item exists or if the path is a bare repository, either of which
+    /// indicates that the path is a repository.
+    fn is_repository(path: &Path) -> bool {
+        // Check for bare repository - e.g. if there is file named "HEAD" in the root
+        let bare = path.join("HEAD").exists();
+        if bare {
+            debug!("found bare repository: {:?}", &path);
+            return true;
+        }
+
+        let git_sub_item = path.join(".git");
+        if git_sub_item.exists() {
+            if git_sub_item.is_dir() {
+                debug!("found target: {:?}", &path.display());
+                return true;
+            } else if git_sub_item.is_file() {
+                debug!("found a worktree: {:?}", &path.display());
+                return true;
+            }
+        }
+        false
+    }
 }
 
 /// An enum that contains 0 to N targets based on the variant.
 #[remain::sorted]
 enum MaybeTarget {
-    /// Contains multiple targets from recursive call(s) of [`TargetCollector::run()`].
-    Multiple(Vec<PathBuf>),
+    /// Contains a single bundle.
+    Bundle(PathBuf),
+    /// Contains multiple targets from recursive call(s) of [`TargetCollector::walk()`].
+    Multiple(Targets),
     /// Does not contain a target.
     None,
-    /// Contains a single target.
+    /// Contains a single repository.
     Single(PathBuf),
 }
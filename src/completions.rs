@@ -0,0 +1,461 @@
+//! This module contains the functionality for generating shell completion scripts from the CLI
+//! definition.
+
+use std::io::{self, Write};
+
+use clap::{Command, ValueEnum, ValueHint};
+
+/// The shells that completion scripts can be generated for.
+#[remain::sorted]
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum Shell {
+    /// Generate a completion script for Bash.
+    Bash,
+    /// Generate a completion script for fish.
+    Fish,
+    /// Generate a completion script for PowerShell.
+    Powershell,
+    /// Generate a completion script for Zsh.
+    Zsh,
+}
+
+/// An option of the CLI, as needed for completions.
+struct Flag {
+    short: Option<char>,
+    long: Option<String>,
+    help: String,
+    value: Value,
+}
+
+/// What an option expects after it.
+enum Value {
+    /// A value that cannot be completed (e.g. a number).
+    Any,
+    /// A path to a file.
+    File,
+    /// No value (i.e. the option is a flag).
+    None,
+    /// One of the given values.
+    OneOf(Vec<String>),
+}
+
+/// A subcommand of the CLI, as needed for completions. Only the possible values of its first
+/// positional argument are completed.
+struct Subcommand {
+    name: String,
+    help: String,
+    values: Vec<String>,
+}
+
+/// Write the completion script for the shell to the writer. Options, values of enum-valued options
+/// and subcommands are completed. Positional arguments are completed as directories.
+pub(crate) fn generate(shell: Shell, command: &Command, writer: &mut impl Write) -> io::Result<()> {
+    let name = command.get_name();
+    let flags = flags(command);
+    let subcommands = subcommands(command);
+    match shell {
+        Shell::Bash => bash(name, &flags, &subcommands, writer),
+        Shell::Fish => fish(name, &flags, &subcommands, writer),
+        Shell::Powershell => powershell(name, &flags, &subcommands, writer),
+        Shell::Zsh => zsh(name, &flags, &subcommands, writer),
+    }
+}
+
+fn flags(command: &Command) -> Vec<Flag> {
+    let mut flags: Vec<Flag> = command
+        .get_arguments()
+        .filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+        .map(|arg| {
+            let possible_values: Vec<String> = arg
+                .get_possible_values()
+                .iter()
+                .filter(|value| !value.is_hide_set())
+                .map(|value| value.get_name().to_string())
+                .collect();
+            let value = if !arg.get_action().takes_values() {
+                Value::None
+            } else if !possible_values.is_empty() {
+                Value::OneOf(possible_values)
+            } else if let ValueHint::FilePath | ValueHint::AnyPath = arg.get_value_hint() {
+                Value::File
+            } else {
+                Value::Any
+            };
+            Flag {
+                short: arg.get_short(),
+                long: arg.get_long().map(str::to_string),
+                help: first_line(arg.get_help().map(ToString::to_string)),
+                value,
+            }
+        })
+        .collect();
+    for (long, short, help) in [
+        ("help", 'h', "Print help"),
+        ("version", 'V', "Print version"),
+    ] {
+        if !flags.iter().any(|flag| flag.long.as_deref() == Some(long)) {
+            flags.push(Flag {
+                short: Some(short),
+                long: Some(long.to_string()),
+                help: help.to_string(),
+                value: Value::None,
+            });
+        }
+    }
+    flags
+}
+
+fn subcommands(command: &Command) -> Vec<Subcommand> {
+    command
+        .get_subcommands()
+        .filter(|subcommand| !subcommand.is_hide_set())
+        .map(|subcommand| Subcommand {
+            name: subcommand.get_name().to_string(),
+            help: first_line(subcommand.get_about().map(ToString::to_string)),
+            values: subcommand
+                .get_positionals()
+                .next()
+                .map(|arg| {
+                    arg.get_possible_values()
+                        .iter()
+                        .map(|value| value.get_name().to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+fn first_line(help: Option<String>) -> String {
+    help.unwrap_or_default()
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+impl Flag {
+    /// The names of the flag with their dashes (e.g. `["-c", "--color-mode"]`).
+    fn names(&self) -> Vec<String> {
+        self.short
+            .map(|short| format!("-{short}"))
+            .into_iter()
+            .chain(self.long.as_ref().map(|long| format!("--{long}")))
+            .collect()
+    }
+}
+
+fn bash(
+    name: &str,
+    flags: &[Flag],
+    subcommands: &[Subcommand],
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    let function = format!("_{}", name.replace('-', "_"));
+    let all_flags = flags
+        .iter()
+        .flat_map(Flag::names)
+        .collect::<Vec<String>>()
+        .join(" ");
+    let subcommand_names = subcommands
+        .iter()
+        .map(|subcommand| subcommand.name.as_str())
+        .collect::<Vec<&str>>()
+        .join(" ");
+
+    writeln!(writer, "{function}() {{")?;
+    writeln!(writer, "    local cur prev")?;
+    writeln!(writer, "    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"")?;
+    writeln!(writer, "    prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"")?;
+    if !subcommands.is_empty() {
+        writeln!(writer, "    if [[ ${{COMP_CWORD}} -eq 2 ]]; then")?;
+        writeln!(writer, "        case \"${{COMP_WORDS[1]}}\" in")?;
+        for subcommand in subcommands {
+            writeln!(writer, "            {})", subcommand.name)?;
+            writeln!(
+                writer,
+                "                COMPREPLY=($(compgen -W \"{}\" -- \"${{cur}}\"))",
+                subcommand.values.join(" ")
+            )?;
+            writeln!(writer, "                return 0")?;
+            writeln!(writer, "                ;;")?;
+        }
+        writeln!(writer, "        esac")?;
+        writeln!(writer, "    fi")?;
+    }
+    writeln!(writer, "    case \"${{prev}}\" in")?;
+    for flag in flags {
+        let reply = match &flag.value {
+            Value::Any => "COMPREPLY=()".to_string(),
+            Value::File => "COMPREPLY=($(compgen -f -- \"${cur}\"))".to_string(),
+            Value::None => continue,
+            Value::OneOf(values) => format!(
+                "COMPREPLY=($(compgen -W \"{}\" -- \"${{cur}}\"))",
+                values.join(" ")
+            ),
+        };
+        writeln!(writer, "        {})", flag.names().join("|"))?;
+        writeln!(writer, "            {reply}")?;
+        writeln!(writer, "            return 0")?;
+        writeln!(writer, "            ;;")?;
+    }
+    writeln!(writer, "    esac")?;
+    writeln!(writer, "    if [[ \"${{cur}}\" == -* ]]; then")?;
+    writeln!(
+        writer,
+        "        COMPREPLY=($(compgen -W \"{all_flags}\" -- \"${{cur}}\"))"
+    )?;
+    if !subcommands.is_empty() {
+        writeln!(writer, "    elif [[ ${{COMP_CWORD}} -eq 1 ]]; then")?;
+        writeln!(
+            writer,
+            "        COMPREPLY=($(compgen -W \"{subcommand_names}\" -- \"${{cur}}\") $(compgen -d -- \"${{cur}}\"))"
+        )?;
+    }
+    writeln!(writer, "    else")?;
+    writeln!(writer, "        COMPREPLY=($(compgen -d -- \"${{cur}}\"))")?;
+    writeln!(writer, "    fi")?;
+    writeln!(writer, "}}")?;
+    writeln!(
+        writer,
+        "complete -F {function} -o filenames -o bashdefault {name}"
+    )
+}
+
+fn zsh(
+    name: &str,
+    flags: &[Flag],
+    subcommands: &[Subcommand],
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    // Descriptions are written within brackets and single quotes.
+    let escape = |help: &str| {
+        help.replace('\'', "'\\''")
+            .replace('[', "\\[")
+            .replace(']', "\\]")
+    };
+    let function = format!("_{}", name.replace('-', "_"));
+
+    writeln!(writer, "#compdef {name}")?;
+    writeln!(writer)?;
+    writeln!(writer, "{function}() {{")?;
+    if !subcommands.is_empty() {
+        writeln!(writer, "    case \"${{words[2]}}\" in")?;
+        for subcommand in subcommands {
+            writeln!(writer, "        {})", subcommand.name)?;
+            writeln!(
+                writer,
+                "            (( CURRENT == 3 )) && _values '{}' {}",
+                subcommand.name,
+                subcommand.values.join(" ")
+            )?;
+            writeln!(writer, "            return")?;
+            writeln!(writer, "            ;;")?;
+        }
+        writeln!(writer, "    esac")?;
+        writeln!(writer)?;
+    }
+    writeln!(writer, "    local state")?;
+    writeln!(writer, "    _arguments -s -C \\")?;
+    for flag in flags {
+        let action = match &flag.value {
+            Value::Any => ":value: ".to_string(),
+            Value::File => ":path:_files".to_string(),
+            Value::None => String::new(),
+            Value::OneOf(values) => format!(":value:({})", values.join(" ")),
+        };
+        let names = flag.names();
+        let help = escape(&flag.help);
+        match names.as_slice() {
+            [single] => writeln!(writer, "        '{single}[{help}]{action}' \\")?,
+            _ => writeln!(
+                writer,
+                "        '({names})'{{{joined}}}'[{help}]{action}' \\",
+                names = names.join(" "),
+                joined = names.join(",")
+            )?,
+        }
+    }
+    match subcommands.is_empty() {
+        true => writeln!(writer, "        '*:path:_directories'")?,
+        false => {
+            writeln!(writer, "        '1: :->first' \\")?;
+            writeln!(writer, "        '*:path:_directories'")?;
+            writeln!(writer)?;
+            writeln!(writer, "    if [[ \"${{state}}\" == first ]]; then")?;
+            writeln!(writer, "        local -a commands=(")?;
+            for subcommand in subcommands {
+                writeln!(
+                    writer,
+                    "            '{}:{}'",
+                    subcommand.name,
+                    escape(&subcommand.help).replace(':', "\\:")
+                )?;
+            }
+            writeln!(writer, "        )")?;
+            writeln!(writer, "        _describe -t commands command commands")?;
+            writeln!(writer, "        _directories")?;
+            writeln!(writer, "    fi")?;
+        }
+    }
+    writeln!(writer, "}}")?;
+    writeln!(writer)?;
+    writeln!(writer, "{function} \"$@\"")
+}
+
+fn fish(
+    name: &str,
+    flags: &[Flag],
+    subcommands: &[Subcommand],
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    let escape = |help: &str| help.replace('\\', "\\\\").replace('\'', "\\'");
+    for subcommand in subcommands {
+        writeln!(
+            writer,
+            "complete -c {name} -n '__fish_use_subcommand' -f -a {} -d '{}'",
+            subcommand.name,
+            escape(&subcommand.help)
+        )?;
+        writeln!(
+            writer,
+            "complete -c {name} -n '__fish_seen_subcommand_from {}' -f -a '{}'",
+            subcommand.name,
+            subcommand.values.join(" ")
+        )?;
+    }
+    for flag in flags {
+        let mut line = format!("complete -c {name}");
+        if let Some(short) = flag.short {
+            line.push_str(&format!(" -s {short}"));
+        }
+        if let Some(long) = &flag.long {
+            line.push_str(&format!(" -l {long}"));
+        }
+        line.push_str(&format!(" -d '{}'", escape(&flag.help)));
+        match &flag.value {
+            Value::Any => line.push_str(" -x"),
+            Value::File => line.push_str(" -r -F"),
+            Value::None => {}
+            Value::OneOf(values) => line.push_str(&format!(" -x -a '{}'", values.join(" "))),
+        }
+        writeln!(writer, "{line}")?;
+    }
+    Ok(())
+}
+
+fn powershell(
+    name: &str,
+    flags: &[Flag],
+    subcommands: &[Subcommand],
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    let quote = |values: &mut dyn Iterator<Item = &str>| {
+        values
+            .map(|value| format!("'{}'", value.replace('\'', "''")))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+
+    writeln!(
+        writer,
+        "Register-ArgumentCompleter -Native -CommandName '{name}' -ScriptBlock {{"
+    )?;
+    writeln!(
+        writer,
+        "    param($wordToComplete, $commandAst, $cursorPosition)"
+    )?;
+    writeln!(
+        writer,
+        "    $elements = @($commandAst.CommandElements | ForEach-Object {{ $_.ToString() }})"
+    )?;
+    writeln!(
+        writer,
+        "    $index = $elements.Count - $(if ($wordToComplete) {{ 1 }} else {{ 0 }})"
+    )?;
+    writeln!(
+        writer,
+        "    $previous = if ($index -gt 0) {{ $elements[$index - 1] }} else {{ '' }}"
+    )?;
+    writeln!(writer, "    $values = switch ($previous) {{")?;
+    for subcommand in subcommands {
+        writeln!(
+            writer,
+            "        {{ $index -eq 2 -and $_ -eq '{}' }} {{ {} ; break }}",
+            subcommand.name,
+            quote(&mut subcommand.values.iter().map(String::as_str))
+        )?;
+    }
+    for flag in flags {
+        let values = match &flag.value {
+            // Returning nothing falls back to completing paths.
+            Value::Any | Value::File => "@()".to_string(),
+            Value::None => continue,
+            Value::OneOf(values) => quote(&mut values.iter().map(String::as_str)),
+        };
+        let names = flag.names();
+        writeln!(
+            writer,
+            "        {{ $_ -cin {} }} {{ {values} ; break }}",
+            quote(&mut names.iter().map(String::as_str))
+        )?;
+    }
+    writeln!(
+        writer,
+        "        {{ $wordToComplete -like '-*' }} {{ {} ; break }}",
+        quote(
+            &mut flags
+                .iter()
+                .flat_map(Flag::names)
+                .collect::<Vec<String>>()
+                .iter()
+                .map(String::as_str)
+        )
+    )?;
+    if !subcommands.is_empty() {
+        writeln!(
+            writer,
+            "        {{ $index -eq 1 }} {{ {} ; break }}",
+            quote(
+                &mut subcommands
+                    .iter()
+                    .map(|subcommand| subcommand.name.as_str())
+            )
+        )?;
+    }
+    writeln!(writer, "    }}")?;
+    writeln!(
+        writer,
+        "    $values | Where-Object {{ $_ -like \"$wordToComplete*\" }} | ForEach-Object {{"
+    )?;
+    writeln!(
+        writer,
+        "        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)"
+    )?;
+    writeln!(writer, "    }}")?;
+    writeln!(writer, "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    use crate::args::Cli;
+
+    #[allow(clippy::panic_in_result_fn)]
+    #[test]
+    fn completion_scripts() -> anyhow::Result<()> {
+        let command = Cli::command();
+        for shell in Shell::value_variants() {
+            let mut script = Vec::new();
+            generate(*shell, &command, &mut script)?;
+            let script = String::from_utf8(script)?;
+            assert!(script.contains("color-mode"), "{shell:?}");
+            assert!(script.contains("compatibility"), "{shell:?}");
+            assert!(script.contains("standard-alphabetical"), "{shell:?}");
+            assert!(script.contains("completions"), "{shell:?}");
+        }
+        Ok(())
+    }
+}
@@ -1,11 +1,19 @@
 //! This module contains the config specification and functionality for creating a config.
 
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow};
 use clap::ValueEnum;
+use log::{debug, warn};
 use serde::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+use std::path::{self, Path, PathBuf};
 use std::{env, fs};
 
+use crate::status::Status;
+
+/// The extensions of supported config files in order of precedence (i.e. TOML is preferred over
+/// JSON when both exist in the same location).
+const CONFIG_FILE_EXTENSIONS: [&str; 2] = ["toml", "json"];
+
 /// This struct is the actual config type consumed through the codebase. It is boostrapped via its
 /// public methods and uses [`EntryConfig`], a private struct, under the hood in order to
 /// deserialize empty, non-existent, partial, and complete config files.
@@ -17,6 +25,89 @@ pub struct Config {
     pub display_mode: DisplayMode,
     /// The color mode for results printed to `stdout`.
     pub color_mode: ColorMode,
+    /// The set of glyphs used for indicators (e.g. statuses in the compact display mode and ahead
+    /// and behind counts) in results printed to `stdout`.
+    pub glyphs: GlyphSet,
+    /// The field that results printed to `stdout` are sorted by (the display mode's default order
+    /// if `None`).
+    pub sort_by: Option<SortBy>,
+    /// Whether or not the order of results printed to `stdout` is reversed.
+    pub reverse: bool,
+    /// Whether or not a summary of the statuses of all results is printed to `stdout` after the
+    /// results in the standard and classic display modes.
+    pub summary: bool,
+    /// Whether or not results are displayed with their full paths instead of their names in the
+    /// standard, classic and compact display modes.
+    pub absolute_paths: bool,
+    /// Whether or not only results whose status is not "clean" are printed to `stdout`.
+    pub dirty_only: bool,
+    /// The minimum [`Status`] (by severity) of results printed to `stdout` (all results are
+    /// printed if `None`).
+    pub min_status: Option<Status>,
+    /// Whether or not only results whose branch is not one of the `primary_branches` (including
+    /// results with a detached HEAD) are printed to `stdout`.
+    pub off_branch: bool,
+    /// Whether or not only results without a remote are printed to `stdout`.
+    pub local_only: bool,
+    /// Whether or not only results with uncommitted changes, unpushed commits or stashes are
+    /// printed to `stdout`.
+    pub only_with_changes_to_push: bool,
+    /// The Git config scope that the email for each result is read from.
+    pub email_scope: EmailScope,
+    /// A regular expression (see [`EmailAllowlist`](crate::collector::EmailAllowlist) for the
+    /// syntax) that the effective email for each result is expected to match (no check if `None`).
+    pub email_allowlist: Option<String>,
+    /// Glob patterns for directories that are pruned from traversal (see
+    /// [`Glob`](crate::glob::Glob) for the syntax).
+    pub exclude: Vec<String>,
+    /// The max number of directory levels below each path that are searched for repositories
+    /// (unlimited if `None`).
+    pub depth: Option<usize>,
+    /// The max depth for specific paths, which takes precedence over `depth` (including when
+    /// `depth` is provided via the CLI). Paths are expanded like `paths`.
+    pub path_depths: BTreeMap<PathBuf, usize>,
+    /// Whether or not bare repositories are skipped.
+    pub skip_bare: bool,
+    /// Whether or not symlinked directories are descended into (they are skipped by default).
+    pub follow_symlinks: bool,
+    /// Whether or not hidden directories (i.e. those whose names start with a dot) are descended
+    /// into (they are skipped by default). The ".git" directory is always skipped.
+    pub include_hidden: bool,
+    /// Whether or not Git bundle files are listed alongside repositories.
+    pub include_bundles: bool,
+    /// Whether or not initialized submodules are listed as repositories of their own (instead of
+    /// being nested under their superprojects, which are marked as such).
+    pub flatten_submodules: bool,
+    /// What the on-disk size of each repository is computed for (not computed if `None`).
+    pub include_size: Option<SizeScope>,
+    /// The number of seconds after which a remote fetch is abandoned (no limit if `None`).
+    pub fetch_timeout_secs: Option<u64>,
+    /// The number of times a remote fetch is retried if it fails due to a transient error.
+    pub fetch_retries: usize,
+    /// The max number of remote fetches that run at the same time (only limited by the number of
+    /// threads if `None`).
+    pub fetch_concurrency: Option<usize>,
+    /// The number of seconds that a successful remote fetch is cached for (no caching if `None`).
+    pub fetch_cache_ttl_secs: Option<u64>,
+    /// Whether or not the statuses of repositories that have not changed since the last run are
+    /// reused (see [`StatusCache`](crate::repository_view::status_cache::StatusCache)).
+    pub status_cache: bool,
+    /// The max width of lines printed to `stdout` in the standard display modes, which defaults to
+    /// the terminal width (no limit if `None` and not displaying to a terminal).
+    pub max_width: Option<usize>,
+    /// A template that each result is printed to `stdout` with (one per line) instead of using the
+    /// display mode (see [`Template`](crate::display::template::Template) for the syntax).
+    pub format: Option<String>,
+    /// The names of primary branches (e.g. "main"). Branches with other names are displayed in bold
+    /// and underlined in the standard and classic display modes.
+    pub primary_branches: Vec<String>,
+    /// The names of remotes in order of preference (e.g. "upstream" before "origin"), which decides
+    /// the remote that is displayed, fetched and compared against for each result.
+    pub remote_preference: Vec<String>,
+    /// Colors for each status, which replace the default colors.
+    pub colors: StatusColors,
+    /// Named groups of paths, which can be collected from instead of `paths`.
+    pub groups: BTreeMap<String, Vec<PathBuf>>,
 }
 
 impl Config {
@@ -30,24 +121,75 @@ impl Config {
         let config_dir = user_dirs::config_dir()?;
         let home_dir = user_dirs::home_dir()?;
 
-        let paths = [
-            config_dir.join("gfold.toml"),
-            config_dir.join("gfold").join("config.toml"),
-            home_dir.join(".config").join("gfold.toml"),
+        // Each location is checked for every supported format (in order of precedence) before
+        // moving on to the next location.
+        let locations = [
+            config_dir.join("gfold"),
+            config_dir.join("gfold").join("config"),
+            home_dir.join(".config").join("gfold"),
         ];
+        let mut paths = locations.iter().flat_map(|location| {
+            CONFIG_FILE_EXTENSIONS
+                .iter()
+                .map(|extension| location.with_extension(extension))
+        });
 
-        let path = match paths.into_iter().find(|p| p.exists()) {
-            Some(path) => path,
-            None => return Self::try_config_default(),
+        match paths.find(|p| p.exists()) {
+            Some(path) => Self::try_config_file(&path),
+            None => Self::try_config_git(),
+        }
+    }
+
+    /// This method uses [`EntryConfig`]'s defaults, but with the paths from the repeated
+    /// "gfold.path" entries in the user's Git config (e.g. `git config --global --add gfold.path
+    /// ~/src`), if there are any. It is used when no config file is found, so a config file always
+    /// takes precedence over the Git config.
+    fn try_config_git() -> Result<Self> {
+        let paths = match git_config_paths() {
+            Ok(paths) => paths,
+            Err(e) => {
+                debug!("ignoring paths from git config (ignored error: {e})");
+                Vec::new()
+            }
         };
+        if paths.is_empty() {
+            return Self::try_config_default();
+        }
+        debug!("using paths from git config: {paths:?}");
+        Self::from_entry_config(&EntryConfig {
+            paths: Some(paths),
+            ..EntryConfig::default()
+        })
+        .context("invalid paths in git config (gfold.path)")
+    }
+
+    /// This method deserializes the config file at the given path (empty, partial or complete)
+    /// without looking in the default locations. Unlike [`Self::try_config()`], it is an error if
+    /// the file does not exist.
+    pub fn try_config_file(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Err(anyhow!("config file not found: {}", path.display()));
+        }
 
-        let contents = fs::read_to_string(path)?;
-        let entry_config = if contents.is_empty() {
+        // Unknown keys are rejected (e.g. typos), so the error points at both the offending key and
+        // the config file that was loaded.
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("could not read config file: {}", path.display()))?;
+        // Files with the "json" extension are JSON and all other files are TOML.
+        let entry_config = if contents.trim().is_empty() {
             EntryConfig::default()
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            serde_json::from_str(&contents)
+                .with_context(|| format!("invalid config file: {}", path.display()))?
         } else {
-            toml::from_str(&contents)?
+            toml::from_str(&contents)
+                .with_context(|| format!("invalid config file: {}", path.display()))?
         };
         Self::from_entry_config(&entry_config)
+            .with_context(|| format!("invalid config file: {}", path.display()))
     }
 
     /// This method does not look for the config file and uses [`EntryConfig`]'s defaults instead.
@@ -90,19 +232,164 @@ impl Config {
                 Some(color_mode) => *color_mode,
                 None => ColorMode::Always,
             },
+            glyphs: entry_config.glyphs.unwrap_or_default(),
+            sort_by: entry_config.sort_by,
+            reverse: entry_config.reverse.unwrap_or_default(),
+            summary: entry_config.summary.unwrap_or(true),
+            absolute_paths: entry_config.absolute_paths.unwrap_or_default(),
+            dirty_only: entry_config.dirty_only.unwrap_or_default(),
+            min_status: entry_config.min_status,
+            off_branch: entry_config.off_branch.unwrap_or_default(),
+            local_only: entry_config.local_only.unwrap_or_default(),
+            only_with_changes_to_push: entry_config.only_with_changes_to_push.unwrap_or_default(),
+            email_scope: entry_config.email_scope.unwrap_or_default(),
+            email_allowlist: entry_config.email_allowlist.clone(),
+            exclude: entry_config.exclude.clone().unwrap_or_default(),
+            depth: entry_config.depth,
+            path_depths: match &entry_config.path_depths {
+                Some(path_depths) => path_depths
+                    .iter()
+                    .map(|(path, depth)| Ok((normalize_path(path)?, *depth)))
+                    .collect::<Result<BTreeMap<PathBuf, usize>>>()?,
+                None => BTreeMap::new(),
+            },
+            skip_bare: entry_config.skip_bare.unwrap_or_default(),
+            follow_symlinks: entry_config.follow_symlinks.unwrap_or_default(),
+            include_hidden: entry_config.include_hidden.unwrap_or_default(),
+            include_bundles: entry_config.include_bundles.unwrap_or_default(),
+            flatten_submodules: entry_config.flatten_submodules.unwrap_or_default(),
+            include_size: entry_config.include_size,
+            fetch_timeout_secs: entry_config.fetch_timeout_secs,
+            fetch_retries: entry_config.fetch_retries.unwrap_or_default(),
+            fetch_concurrency: entry_config.fetch_concurrency,
+            fetch_cache_ttl_secs: entry_config.fetch_cache_ttl_secs,
+            status_cache: entry_config.status_cache.unwrap_or_default(),
+            max_width: entry_config.max_width,
+            format: entry_config.format.clone(),
+            primary_branches: entry_config
+                .primary_branches
+                .clone()
+                .unwrap_or_else(|| vec!["main".to_string(), "master".to_string()]),
+            remote_preference: entry_config
+                .remote_preference
+                .clone()
+                .unwrap_or_else(|| vec!["origin".to_string()]),
+            colors: entry_config.colors.clone().unwrap_or_default(),
+            groups: match &entry_config.groups {
+                Some(groups) => groups
+                    .iter()
+                    .map(|(name, paths)| {
+                        let paths = paths.iter().map(|p| normalize_path(p)).collect::<Result<
+                            Vec<PathBuf>,
+                            _,
+                        >>(
+                        )?;
+                        Ok((name.clone(), paths))
+                    })
+                    .collect::<Result<BTreeMap<String, Vec<PathBuf>>>>()?,
+                None => BTreeMap::new(),
+            },
         })
     }
+
+    /// Replace `paths` with the paths from the group with the given name.
+    pub fn select_group(&mut self, name: &str) -> Result<()> {
+        self.paths = self
+            .groups
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("group not found in config: {name}"))?
+            .clone();
+        Ok(())
+    }
+}
+
+/// Read the values of every "gfold.path" entry from the user's Git config (i.e. the global, XDG
+/// and system config files).
+fn git_config_paths() -> Result<Vec<PathBuf>, git2::Error> {
+    let config = git2::Config::open_default()?;
+    let mut paths = Vec::new();
+    match config.multivar("gfold.path", None) {
+        Ok(entries) => entries.for_each(|entry| {
+            if let Some(value) = entry.value() {
+                paths.push(PathBuf::from(value));
+            }
+        })?,
+        Err(e) if e.code() == git2::ErrorCode::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    Ok(paths)
 }
 
+/// Expand the path (see [`expand_path()`]) and resolve it (see [`resolve_path()`]).
 fn normalize_path(path: &Path) -> Result<PathBuf> {
-    Ok(match path
-        .strip_prefix("~")
-        .or_else(|_| path.strip_prefix("$HOME"))
-    {
-        Ok(stripped) => user_dirs::home_dir()?.join(stripped),
-        Err(_) => path.to_path_buf(),
+    resolve_path(&expand_path(path)?)
+}
+
+/// Canonicalize the path so that it is absolute and results derived from it are stable regardless
+/// of the current working directory. If the path exists but cannot be canonicalized (e.g. it is a
+/// broken symlink), the absolute path is used as-is with a warning.
+pub fn resolve_path(path: &Path) -> Result<PathBuf> {
+    match path.canonicalize() {
+        Ok(canonical) => Ok(canonical),
+        Err(e) if fs::symlink_metadata(path).is_ok() => {
+            warn!(
+                "could not canonicalize path ({e}); using it as-is: {}",
+                path.display()
+            );
+            path::absolute(path)
+                .with_context(|| format!("could not resolve path: {}", path.display()))
+        }
+        Err(e) => Err(e).with_context(|| format!("could not resolve path: {}", path.display())),
     }
-    .canonicalize()?)
+}
+
+/// Expand a leading `~` to the home directory as well as environment variables in the form of
+/// `$NAME` or `${NAME}` anywhere in the path. This works the same way on all platforms. Paths that
+/// are not valid UTF-8 are not expanded.
+pub fn expand_path(path: &Path) -> Result<PathBuf> {
+    let Some(raw) = path.to_str() else {
+        return Ok(path.to_path_buf());
+    };
+    let (mut expanded, rest) = match raw.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            (user_dirs::home_dir()?.to_string_lossy().into_owned(), rest)
+        }
+        _ => (String::new(), raw),
+    };
+
+    let mut chars = rest.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        }
+        let braced = chars.next_if_eq(&'{').is_some();
+        let mut name = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+            name.push(c);
+        }
+        if braced && chars.next() != Some('}') {
+            return Err(anyhow!("unclosed variable in path: {raw}"));
+        }
+        if name.is_empty() {
+            // A lone "$" is kept as is.
+            expanded.push('$');
+            if braced {
+                return Err(anyhow!("empty variable in path: {raw}"));
+            }
+            continue;
+        }
+        let value = match name.as_str() {
+            "HOME" => match env::var("HOME") {
+                Ok(value) => value,
+                Err(_) => user_dirs::home_dir()?.to_string_lossy().into_owned(),
+            },
+            _ => env::var(&name)
+                .map_err(|_| anyhow!("undefined environment variable ({name}) in path: {raw}"))?,
+        };
+        expanded.push_str(&value);
+    }
+    Ok(PathBuf::from(expanded))
 }
 
 /// This struct is a reflection of [`Config`] with its fields wrapped with [`Option`], which
@@ -111,6 +398,7 @@ fn normalize_path(path: &Path) -> Result<PathBuf> {
 /// manually set defaults for the user. For those reasons, the public methods for [`Config`] use
 /// this struct privately.
 #[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 struct EntryConfig {
     /// Formerly a reflection of the `path` field on [`Config`]. Use `paths` instead.
     /// This field is deprecated and will be removed in a future release.
@@ -121,6 +409,95 @@ struct EntryConfig {
     pub display_mode: Option<DisplayMode>,
     /// Reflection of the `color_mode` field on [`Config`].
     pub color_mode: Option<ColorMode>,
+    /// Reflection of the `glyphs` field on [`Config`].
+    pub glyphs: Option<GlyphSet>,
+    /// Reflection of the `sort_by` field on [`Config`].
+    pub sort_by: Option<SortBy>,
+    /// Reflection of the `reverse` field on [`Config`].
+    pub reverse: Option<bool>,
+    /// Reflection of the `summary` field on [`Config`].
+    pub summary: Option<bool>,
+    /// Reflection of the `absolute_paths` field on [`Config`].
+    pub absolute_paths: Option<bool>,
+    /// Reflection of the `dirty_only` field on [`Config`].
+    pub dirty_only: Option<bool>,
+    /// Reflection of the `min_status` field on [`Config`].
+    pub min_status: Option<Status>,
+    /// Reflection of the `off_branch` field on [`Config`].
+    pub off_branch: Option<bool>,
+    /// Reflection of the `local_only` field on [`Config`].
+    pub local_only: Option<bool>,
+    /// Reflection of the `only_with_changes_to_push` field on [`Config`].
+    pub only_with_changes_to_push: Option<bool>,
+    /// Reflection of the `email_scope` field on [`Config`].
+    pub email_scope: Option<EmailScope>,
+    /// Reflection of the `email_allowlist` field on [`Config`].
+    pub email_allowlist: Option<String>,
+    /// Reflection of the `exclude` field on [`Config`].
+    pub exclude: Option<Vec<String>>,
+    /// Reflection of the `depth` field on [`Config`].
+    pub depth: Option<usize>,
+    /// Reflection of the `path_depths` field on [`Config`].
+    pub path_depths: Option<BTreeMap<PathBuf, usize>>,
+    /// Reflection of the `skip_bare` field on [`Config`].
+    pub skip_bare: Option<bool>,
+    /// Reflection of the `follow_symlinks` field on [`Config`].
+    pub follow_symlinks: Option<bool>,
+    /// Reflection of the `include_hidden` field on [`Config`].
+    pub include_hidden: Option<bool>,
+    /// Reflection of the `include_bundles` field on [`Config`].
+    pub include_bundles: Option<bool>,
+    /// Reflection of the `flatten_submodules` field on [`Config`].
+    pub flatten_submodules: Option<bool>,
+    /// Reflection of the `include_size` field on [`Config`].
+    pub include_size: Option<SizeScope>,
+    /// Reflection of the `fetch_timeout_secs` field on [`Config`].
+    pub fetch_timeout_secs: Option<u64>,
+    /// Reflection of the `fetch_retries` field on [`Config`].
+    pub fetch_retries: Option<usize>,
+    /// Reflection of the `fetch_concurrency` field on [`Config`].
+    pub fetch_concurrency: Option<usize>,
+    /// Reflection of the `fetch_cache_ttl_secs` field on [`Config`].
+    pub fetch_cache_ttl_secs: Option<u64>,
+    /// Reflection of the `status_cache` field on [`Config`].
+    pub status_cache: Option<bool>,
+    /// Reflection of the `max_width` field on [`Config`].
+    pub max_width: Option<usize>,
+    /// Reflection of the `format` field on [`Config`].
+    pub format: Option<String>,
+    /// Reflection of the `primary_branches` field on [`Config`].
+    pub primary_branches: Option<Vec<String>>,
+    /// Reflection of the `remote_preference` field on [`Config`].
+    pub remote_preference: Option<Vec<String>>,
+    /// Reflection of the `colors` field on [`Config`].
+    pub colors: Option<StatusColors>,
+    /// Reflection of the `groups` field on [`Config`].
+    pub groups: Option<BTreeMap<String, Vec<PathBuf>>>,
+}
+
+/// Colors for each [`Status`](crate::status::Status) that are used instead of the defaults when
+/// displaying with colors. Each color is either a name ("black", "blue", "green", "red", "cyan",
+/// "magenta", "yellow" or "white"), an ANSI 256 color number (e.g. "208"), an RGB triple (e.g.
+/// "255,128,0") or a hex value (e.g. "#ff8000").
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatusColors {
+    /// The color for the "bare" status (defaults to red).
+    pub bare: Option<String>,
+    /// The color for the "clean" status (defaults to green).
+    pub clean: Option<String>,
+    /// The color for the "conflicted" status (defaults to red).
+    pub conflicted: Option<String>,
+    /// The color for the "diverged" status (defaults to cyan).
+    pub diverged: Option<String>,
+    /// The color for the "unclean" status (defaults to yellow).
+    pub unclean: Option<String>,
+    /// The color for the "unknown" status (defaults to red).
+    pub unknown: Option<String>,
+    /// The color for the "unpulled" status (defaults to magenta).
+    pub unpulled: Option<String>,
+    /// The color for the "unpushed" status (defaults to blue).
+    pub unpushed: Option<String>,
 }
 
 /// Dictates how the results gathered should be displayed to the user via `stdout`. Setting this
@@ -135,14 +512,30 @@ struct EntryConfig {
 pub enum DisplayMode {
     /// Informs the caller to display results in the classic format.
     Classic,
+    /// Informs the caller to display results in a dense format with one line per repository (its
+    /// name, a status glyph, its branch and its url) aligned in columns.
+    Compact,
     /// Informs the caller to display results in JSON format.
     Json,
+    /// Informs the caller to stream results in newline-delimited JSON format (one
+    /// [`RepositoryView`](crate::repository_view::RepositoryView) per line) as soon as each
+    /// result is collected.
+    Ndjson,
+    /// Informs the caller to display results in a stable, tab-separated format without colors or
+    /// padding (one repository per line with its path, status, branch, url and email).
+    Porcelain,
     /// Informs the caller to display results in the standard (default) format. All results are
     /// sorted alphabetically and then sorted by status.
     Standard,
     /// Informs the caller to display results in the standard (default) format with a twist: all
     /// results are solely sorted alphabetically (i.e. no additional sort by status).
     StandardAlphabetical,
+    /// Informs the caller to display results in the standard (default) format, grouped under a
+    /// header for each parent directory. Results in each group are sorted alphabetically.
+    StandardGrouped,
+    /// Informs the caller to display results in the standard (default) format with additional
+    /// details (e.g. modified, untracked and staged file counts).
+    Verbose,
 }
 
 /// Set the color mode of results printed to `stdout`.
@@ -157,3 +550,58 @@ pub enum ColorMode {
     /// Never display colors.
     Never,
 }
+
+/// Set the glyphs used for indicators, which can be limited for terminals (and logs) without good
+/// Unicode support.
+#[remain::sorted]
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GlyphSet {
+    /// Only use plain ASCII characters (e.g. "*" for "unclean" and "+" for "unpushed").
+    Ascii,
+    /// Use emoji, which are displayed in color by most terminals regardless of the color mode.
+    Emoji,
+    /// Use Unicode symbols and arrows (default behavior).
+    #[default]
+    Unicode,
+}
+
+/// Set the Git config scope that the email (i.e. "user.email") is read from.
+#[remain::sorted]
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, ValueEnum)]
+pub enum EmailScope {
+    /// Read the email that Git would use, which is the local value if set and the global value
+    /// otherwise (default behavior).
+    #[default]
+    Effective,
+    /// Only read the email from the global config.
+    GlobalOnly,
+    /// Only read the email from the repository's local config (e.g. in order to find repositories
+    /// without an overridden email).
+    LocalOnly,
+}
+
+/// Set what the on-disk size of a repository is computed for.
+#[remain::sorted]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SizeScope {
+    /// Only compute the size of the Git directory (e.g. ".git").
+    Git,
+    /// Compute the size of the entire working tree, including the Git directory (bare repositories
+    /// only have the latter).
+    Worktree,
+}
+
+/// Set the field that results printed to `stdout` are sorted by. Ties are broken by name.
+#[remain::sorted]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ValueEnum)]
+pub enum SortBy {
+    /// Sort results by the name of the current, open branch.
+    Branch,
+    /// Sort results by the directory name of the repository.
+    Name,
+    /// Sort results by the full path of the repository.
+    Path,
+    /// Sort results by status from least to most in need of attention (e.g. "clean" results are
+    /// displayed first).
+    Status,
+}
@@ -1,113 +1,712 @@
-//! This module contains the functionality for displaying reports to `stdout`.
+//! This module contains the functionality for displaying reports to `stdout` (or a file).
 
-use std::io;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, IsTerminal, Write};
 use std::path::Path;
+use std::sync::Arc;
 
-use anyhow::{Result, anyhow};
-use color::ColorHarness;
+use anyhow::{Context, Result, anyhow};
+use color::{ColorHarness, Output, StatusPalette};
 use log::debug;
 use log::warn;
+use serde::Serialize;
+use template::Template;
 
+use crate::bundle_view::BundleView;
 use crate::collector::RepositoryCollection;
-use crate::config::{ColorMode, DisplayMode};
+use crate::config::{ColorMode, Config, DisplayMode, GlyphSet, SortBy};
+use crate::repository_view::RepositoryView;
+use crate::repository_view::error_log::{ErrorLog, RepositoryError};
+use crate::repository_view::submodule_view::SubmoduleView;
+use crate::status::Status;
 
 // TODO(nick): make this module private.
 pub mod color;
+pub(crate) mod template;
+mod terminal;
 
 const PAD: usize = 2;
 const NONE: &str = "none";
+const ALL_CLEAN: &str = "all clean";
+
+/// The version of the JSON output's schema, which follows semantic versioning independently of
+/// the crate version: fields are only added within a major version and removing or changing a
+/// field requires a new major version.
+const JSON_SCHEMA_VERSION: &str = "1.7.0";
+
+/// The top-level object of the JSON output.
+#[derive(Debug, Serialize)]
+struct JsonOutput<'a> {
+    schema_version: &'static str,
+    repositories: &'a [RepositoryView],
+    errors: Vec<RepositoryError>,
+}
 
 /// This struct is used for displaying the contents of a [`RepositoryCollection`] to `stdout`.
 #[derive(Debug)]
 pub struct DisplayHarness {
     display_mode: DisplayMode,
     color_mode: ColorMode,
+    glyphs: GlyphSet,
+    sort_by: Option<SortBy>,
+    reverse: bool,
+    dirty_only: bool,
+    max_width: Option<usize>,
+    align: bool,
+    template: Option<Template>,
+    summary: bool,
+    absolute_paths: bool,
+    palette: StatusPalette,
+    primary_branches: Vec<String>,
+    output: Output,
+    summary_only: bool,
+    null_terminated: bool,
+    error_log: Option<ErrorLog>,
 }
 
 impl DisplayHarness {
-    pub fn new(display_mode: DisplayMode, color_mode: ColorMode) -> Self {
-        Self {
-            display_mode,
-            color_mode,
+    /// Create a new display harness using the display options from the [`Config`]. Results are
+    /// written to the output file (which is created or truncated) if one is provided and to
+    /// `stdout` otherwise. When displaying to a terminal, columns are aligned and lines are
+    /// truncated to the terminal width (unless a max width is provided). Otherwise, lines are only
+    /// truncated if a max width is provided. An error is returned if the format is not a valid
+    /// [`Template`], if a configured status color is invalid or if the output file cannot be
+    /// created.
+    pub fn new(config: &Config, output_file: Option<&Path>) -> Result<Self> {
+        let output = match output_file {
+            Some(output_file) => {
+                Output::File(Arc::new(File::create(output_file).with_context(|| {
+                    format!("could not create output file: {}", output_file.display())
+                })?))
+            }
+            None => Output::Stdout,
+        };
+        let align = matches!(output, Output::Stdout) && io::stdout().is_terminal();
+        Ok(Self {
+            max_width: match align {
+                true => config.max_width.or_else(terminal::width),
+                false => config.max_width,
+            },
+            align,
+            display_mode: config.display_mode,
+            color_mode: config.color_mode,
+            glyphs: config.glyphs,
+            sort_by: config.sort_by,
+            reverse: config.reverse,
+            dirty_only: config.dirty_only,
+            template: config.format.as_deref().map(Template::parse).transpose()?,
+            summary: config.summary,
+            absolute_paths: config.absolute_paths,
+            palette: StatusPalette::new(&config.colors)?,
+            primary_branches: config.primary_branches.clone(),
+            output,
+            summary_only: false,
+            null_terminated: false,
+            error_log: None,
+        })
+    }
+
+    /// Skip displaying reports, bundles and group headers entirely and only display the summary
+    /// (in every display mode, even if the summary is disabled). This is useful for measuring the
+    /// cost of collection on its own.
+    pub fn summary_only(mut self, summary_only: bool) -> Self {
+        self.summary_only = summary_only;
+        self
+    }
+
+    /// Terminate each record with a NUL byte instead of a newline in the porcelain display mode
+    /// and with format templates (like `find -print0`), so that paths containing newlines can be
+    /// parsed safely. Fields within a record are still separated by tabs.
+    pub fn null_terminated(mut self, null_terminated: bool) -> Self {
+        self.null_terminated = null_terminated;
+        self
+    }
+
+    /// Include the errors recorded in the error log (i.e. for repositories that could not be
+    /// collected or fetched) in the JSON display mode. The log is shared with the
+    /// [`CollectOptions`](crate::collector::CollectOptions) used for collecting.
+    pub fn error_log(mut self, error_log: Option<ErrorLog>) -> Self {
+        self.error_log = error_log;
+        self
+    }
+
+    /// The terminator written after each record in the porcelain display mode and with format
+    /// templates.
+    fn record_terminator(&self) -> char {
+        match self.null_terminated {
+            true => '\0',
+            false => '\n',
         }
     }
 
-    /// This function chooses the display execution function based on the [`DisplayMode`] provided.
+    /// Get a writer for the output (i.e. a file or `stdout`).
+    fn out(&self) -> Box<dyn Write + '_> {
+        self.output.writer()
+    }
+
+    /// This function chooses the display execution function based on the [`DisplayMode`] provided
+    /// (unless a [`Template`] was provided, which is used instead).
     pub fn run(&self, reports: &RepositoryCollection) -> Result<()> {
+        if self.summary_only {
+            return Ok(());
+        }
+        if let Some(template) = &self.template {
+            return self.template(reports, template);
+        }
+
         match self.display_mode {
-            DisplayMode::Standard => Self::standard(reports, self.color_mode, false)?,
-            DisplayMode::StandardAlphabetical => Self::standard(reports, self.color_mode, true)?,
-            DisplayMode::Json => Self::json(reports)?,
-            DisplayMode::Classic => Self::classic(reports, self.color_mode)?,
+            DisplayMode::Standard => self.standard(reports, false, false)?,
+            DisplayMode::StandardAlphabetical => self.standard(reports, true, false)?,
+            DisplayMode::StandardGrouped => self.standard_grouped(reports)?,
+            DisplayMode::Verbose => self.standard(reports, false, true)?,
+            DisplayMode::Json => self.json(reports)?,
+            DisplayMode::Ndjson => {
+                for report in reports.values().flatten() {
+                    self.ndjson(report)?;
+                }
+            }
+            DisplayMode::Porcelain => self.porcelain(reports)?,
+            DisplayMode::Classic => self.classic(reports)?,
+            DisplayMode::Compact => self.compact(reports)?,
         }
         Ok(())
     }
 
-    /// Display [`RepositoryCollection`] to `stdout` in the standard (default) format.
+    /// When only dirty results are displayed, tell the user that there is nothing to display rather
+    /// than staying silent. This is decided once from the statuses of the reports for all paths.
+    /// Modes intended for scripting display nothing (or an empty collection) instead.
+    pub fn all_clean(&self, statuses: &[Status]) -> io::Result<()> {
+        if self.dirty_only
+            && statuses.is_empty()
+            && !self.summary_only
+            && self.template.is_none()
+            && !matches!(
+                self.display_mode,
+                DisplayMode::Json | DisplayMode::Ndjson | DisplayMode::Porcelain
+            )
+        {
+            writeln!(self.out(), "{ALL_CLEAN}")?;
+        }
+        Ok(())
+    }
+
+    /// Display views of bundles after the reports. Only the standard, classic and compact display
+    /// modes display bundles. Unreadable bundles are displayed with the "unknown" status.
+    pub fn bundles(&self, bundles: &[BundleView]) -> Result<()> {
+        if bundles.is_empty() || self.template.is_some() || self.summary_only {
+            return Ok(());
+        }
+        let color_harness = ColorHarness::new(self.color_mode, self.palette, self.output.clone());
+        match self.display_mode {
+            DisplayMode::Standard
+            | DisplayMode::StandardAlphabetical
+            | DisplayMode::StandardGrouped
+            | DisplayMode::Verbose => {
+                for bundle in bundles {
+                    color_harness.write_bold(&bundle.name, false)?;
+                    let full_path = match &bundle.parent {
+                        Some(parent) => Path::new(parent).join(&bundle.name),
+                        None => Path::new(&bundle.name).to_path_buf(),
+                    };
+                    let full_path = full_path.display().to_string();
+                    let full_path = self.truncate(&full_path, bundle.name.chars().count() + 3);
+                    color_harness.write_gray(&format!(" ~ {full_path}"), true)?;
+
+                    write!(self.out(), "  ")?;
+                    let Some(heads) = &bundle.heads else {
+                        color_harness.write_status(Status::Unknown, PAD)?;
+                        writeln!(self.out())?;
+                        continue;
+                    };
+                    writeln!(
+                        self.out(),
+                        "bundle ({} {})",
+                        heads.len(),
+                        match heads.len() {
+                            1 => "head",
+                            _ => "heads",
+                        }
+                    )?;
+                    if !heads.is_empty() {
+                        let names = heads
+                            .iter()
+                            .map(|head| head.name.as_str())
+                            .collect::<Vec<&str>>()
+                            .join(" ");
+                        writeln!(self.out(), "  {}", self.truncate(&names, 2))?;
+                    }
+                }
+            }
+            DisplayMode::Classic | DisplayMode::Compact => {
+                let name_max = bundles
+                    .iter()
+                    .map(|bundle| bundle.name.len())
+                    .max()
+                    .unwrap_or_default();
+                for bundle in bundles {
+                    write!(
+                        self.out(),
+                        "{:<name_width$}",
+                        bundle.name,
+                        name_width = name_max + PAD
+                    )?;
+                    match &bundle.heads {
+                        Some(heads) => {
+                            writeln!(self.out(), "bundle{}{}", " ".repeat(PAD), heads.len())?
+                        }
+                        None => {
+                            color_harness.write_status(Status::Unknown, PAD)?;
+                            writeln!(self.out())?;
+                        }
+                    }
+                }
+            }
+            DisplayMode::Json | DisplayMode::Ndjson | DisplayMode::Porcelain => {
+                debug!("bundles are not displayed in this display mode");
+            }
+        }
+        Ok(())
+    }
+
+    /// Display a header for the group of paths that reports are collected from. Only the standard
+    /// display modes display the header.
+    pub fn group_header(&self, group: &str) -> io::Result<()> {
+        if self.template.is_some() || self.summary_only {
+            return Ok(());
+        }
+        if let DisplayMode::Standard
+        | DisplayMode::StandardAlphabetical
+        | DisplayMode::StandardGrouped
+        | DisplayMode::Verbose = self.display_mode
+        {
+            ColorHarness::new(self.color_mode, self.palette, self.output.clone())
+                .write_bold(&format!("[{group}]"), true)?;
+        }
+        Ok(())
+    }
+
+    /// Display a summary with the number of reports for each [`Status`] (ordered by severity), e.g.
+    /// "42 repos: 30 clean, 8 unclean, 3 unpushed, 1 unknown." Only the standard, classic and
+    /// compact display modes display the summary and nothing is displayed if there are no reports
+    /// (unless only the summary is displayed, in which case it is always displayed).
+    pub fn summary(&self, statuses: &[Status]) -> io::Result<()> {
+        if self.summary_only && statuses.is_empty() {
+            writeln!(self.out(), "0 repos.")?;
+            return Ok(());
+        }
+        if !self.summary_only
+            && (!self.summary
+                || self.template.is_some()
+                || statuses.is_empty()
+                || !matches!(
+                    self.display_mode,
+                    DisplayMode::Classic
+                        | DisplayMode::Compact
+                        | DisplayMode::Standard
+                        | DisplayMode::StandardAlphabetical
+                        | DisplayMode::StandardGrouped
+                        | DisplayMode::Verbose
+                ))
+        {
+            return Ok(());
+        }
+        let mut counts = BTreeMap::new();
+        for status in statuses {
+            *counts
+                .entry((status.severity(), status.as_str()))
+                .or_insert(0) += 1;
+        }
+        let counts = counts
+            .iter()
+            .map(|((_, status), count)| format!("{count} {status}"))
+            .collect::<Vec<String>>();
+        if !self.summary_only {
+            writeln!(self.out())?;
+        }
+        writeln!(
+            self.out(),
+            "{} {}: {}.",
+            statuses.len(),
+            match statuses.len() {
+                1 => "repo",
+                _ => "repos",
+            },
+            counts.join(", ")
+        )?;
+        Ok(())
+    }
+
+    /// Sort reports by the provided [`SortBy`] (ties are broken by name). If not provided, reports
+    /// are sorted by name and then (unless only sorting alphabetically) by status. The order is
+    /// reversed afterwards, if needed.
+    fn sort(&self, reports: &mut [RepositoryView], alphabetical_sort_only: bool) {
+        reports.sort_by(|a, b| a.name.cmp(&b.name));
+        match self.sort_by {
+            Some(sort_by) => reports.sort_by(|a, b| Self::compare(sort_by, a, b)),
+            None if !alphabetical_sort_only => {
+                reports.sort_by(|a, b| a.status.as_str().cmp(b.status.as_str()))
+            }
+            None => {}
+        }
+        if self.reverse {
+            reports.reverse();
+        }
+    }
+
+    fn compare(sort_by: SortBy, a: &RepositoryView, b: &RepositoryView) -> Ordering {
+        match sort_by {
+            SortBy::Branch => a.branch.cmp(&b.branch),
+            SortBy::Name => a.name.cmp(&b.name),
+            SortBy::Path => (&a.parent, &a.name).cmp(&(&b.parent, &b.name)),
+            SortBy::Status => a.status.severity().cmp(&b.status.severity()),
+        }
+    }
+
+    /// Display [`RepositoryCollection`] to `stdout` in the standard (default) format. If verbose,
+    /// additional details are displayed for each report.
     fn standard(
+        &self,
         reports: &RepositoryCollection,
-        color_mode: ColorMode,
         alphabetical_sort_only: bool,
+        verbose: bool,
     ) -> Result<()> {
-        debug!("detected standard display mode");
+        debug!("detected standard display mode (verbose: {verbose})");
         let mut all_reports = Vec::new();
         for grouped_report in reports {
             all_reports.append(&mut grouped_report.1.clone());
         }
+        self.sort(&mut all_reports, alphabetical_sort_only);
+
+        let color_harness = ColorHarness::new(self.color_mode, self.palette, self.output.clone());
 
-        all_reports.sort_by(|a, b| a.name.cmp(&b.name));
-        if !alphabetical_sort_only {
-            all_reports.sort_by(|a, b| a.status.as_str().cmp(b.status.as_str()));
+        // Statuses are only aligned when displaying to a terminal.
+        let status_width = match self.align {
+            true => all_reports
+                .iter()
+                .map(|report| report.status.as_str().len())
+                .max()
+                .unwrap_or_default(),
+            false => PAD,
+        };
+
+        for report in &all_reports {
+            self.standard_report(&color_harness, report, status_width, verbose, false)?;
         }
+        Ok(())
+    }
 
-        let color_harness = ColorHarness::new(color_mode);
+    /// Display [`RepositoryCollection`] to `stdout` in the standard format, grouped under a header
+    /// for each parent directory. Groups are sorted by path and reports within each group are
+    /// sorted by name (unless sorting by another field).
+    fn standard_grouped(&self, reports: &RepositoryCollection) -> Result<()> {
+        debug!("detected standard grouped display mode");
+        let mut groups: BTreeMap<Option<&str>, Vec<RepositoryView>> = BTreeMap::new();
+        for report in reports.values().flatten() {
+            groups
+                .entry(report.parent.as_deref())
+                .or_default()
+                .push(report.clone());
+        }
 
-        for report in all_reports {
-            color_harness.write_bold(&report.name, false)?;
+        let color_harness = ColorHarness::new(self.color_mode, self.palette, self.output.clone());
+        let status_width = match self.align {
+            true => reports
+                .values()
+                .flatten()
+                .map(|report| report.status.as_str().len())
+                .max()
+                .unwrap_or_default(),
+            false => PAD,
+        };
 
-            let Some(parent) = report.parent else {
+        // Reports are indented under each header, so groups are not separated by blank lines.
+        for (parent, mut group) in groups {
+            color_harness.write_bold(&self.truncate(parent.unwrap_or(NONE), 0), true)?;
+            self.sort(&mut group, true);
+            for report in &group {
+                self.standard_report(&color_harness, report, status_width, false, true)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Display a single report in the standard format. If grouped, the report is indented and its
+    /// path is not displayed (i.e. since it is in the header).
+    fn standard_report(
+        &self,
+        color_harness: &ColorHarness,
+        report: &RepositoryView,
+        status_width: usize,
+        verbose: bool,
+        grouped: bool,
+    ) -> Result<()> {
+        let indent = match grouped {
+            true => "  ",
+            false => "",
+        };
+        write!(self.out(), "{indent}")?;
+        // The path is already displayed in place of the name when using absolute paths.
+        color_harness.write_bold(&self.name(report), grouped || self.absolute_paths)?;
+        if !grouped && !self.absolute_paths {
+            let Some(parent) = &report.parent else {
                 warn!("parent is empty for collector: {}", report.name);
-                continue;
+                return Ok(());
             };
             let full_path = Path::new(&parent).join(&report.name);
-            let full_path_formatted = format!(
-                " ~ {}",
-                full_path.to_str().ok_or(anyhow!(
-                    "could not convert path (Path) to &str: {full_path:?}"
-                ))?
-            );
-            color_harness.write_gray(&full_path_formatted, true)?;
-
-            print!("  ");
-            color_harness.write_status(report.status, PAD)?;
-            println!(" ({})", report.branch);
-            if let Some(url) = &report.url {
-                println!("  {url}");
-            }
-            if let Some(email) = &report.email {
-                println!("  {email}");
-            }
+            let full_path = full_path.to_str().ok_or(anyhow!(
+                "could not convert path (Path) to &str: {full_path:?}"
+            ))?;
+            let full_path = self.truncate(full_path, report.name.chars().count() + 3);
+            color_harness.write_gray(&format!(" ~ {full_path}"), true)?;
+        }
+
+        let mut counts = String::new();
+        let (ahead, behind) = arrows(self.glyphs);
+        if report.ahead > 0 {
+            counts.push_str(&format!(" {ahead}{}", report.ahead));
+        }
+        if report.behind > 0 {
+            counts.push_str(&format!(" {behind}{}", report.behind));
+        }
+        if report.stashes > 0 {
+            counts.push_str(&format!(" stash:{}", report.stashes));
+        }
+        if let Some(operation) = &report.operation {
+            counts.push_str(&format!(" op:{operation}"));
+        }
+        if report.superproject {
+            counts.push_str(" superproject");
+        }
+        if report.url.is_none() {
+            counts.push_str(" local-only");
+        }
+        if let Some(stale_branches @ 1..) = report.stale_branches {
+            counts.push_str(&format!(" prunable:{stale_branches}"));
+        }
+        if report.email_allowed == Some(false) {
+            counts.push_str(" email:disallowed");
+        }
+        // Submodules are only collected for some display modes, so the indicator displays how
+        // many initialized submodules are "clean" only if there are any.
+        let initialized = report
+            .submodules
+            .iter()
+            .filter(|submodule| submodule.initialized)
+            .collect::<Vec<_>>();
+        if !initialized.is_empty() {
+            let clean = initialized
+                .iter()
+                .filter(|submodule| submodule.status == Status::Clean)
+                .count();
+            counts.push_str(&format!(" sub:{clean}/{}", initialized.len()));
+        }
+        let used_width = indent.len()
+            + 2
+            + status_width.max(report.status.as_str().len())
+            + 3
+            + counts.chars().count();
+        let branch = self.truncate(&report.branch, used_width);
+
+        write!(self.out(), "{indent}  ")?;
+        color_harness.write_status(report.status, status_width)?;
+        write!(self.out(), " (")?;
+        color_harness.write_branch(&branch, self.is_primary(&report.branch))?;
+        writeln!(self.out(), "){counts}")?;
+        let used_width = indent.len() + 2;
+        if let Some(url) = &report.url {
+            writeln!(self.out(), "{indent}  {}", self.truncate(url, used_width))?;
+        }
+        if let Some(email) = &report.email {
+            writeln!(self.out(), "{indent}  {}", self.truncate(email, used_width))?;
+        }
+        if verbose {
+            writeln!(
+                self.out(),
+                "{indent}  {}",
+                self.truncate(&Self::details(report).join(" "), used_width)
+            )?;
         }
         Ok(())
     }
 
-    /// Display [`RepositoryCollection`] to `stdout` in JSON format.
-    fn json(reports: &RepositoryCollection) -> serde_json::Result<()> {
+    /// The name that the report is displayed with, which is its full path (i.e. the parent joined
+    /// with the name) when using absolute paths.
+    fn name<'a>(&self, report: &'a RepositoryView) -> Cow<'a, str> {
+        match (&report.parent, self.absolute_paths) {
+            (Some(parent), true) => {
+                Cow::Owned(Path::new(parent).join(&report.name).display().to_string())
+            }
+            _ => Cow::Borrowed(&report.name),
+        }
+    }
+
+    /// Check if the branch is one of the primary branches.
+    fn is_primary(&self, branch: &str) -> bool {
+        self.primary_branches
+            .iter()
+            .any(|primary| primary == branch)
+    }
+
+    /// Truncate the input with an ellipsis (see [`ellipsis()`]) if it does not fit within the max
+    /// width, taking the width already used on the line into account.
+    fn truncate<'a>(&self, input: &'a str, used_width: usize) -> Cow<'a, str> {
+        let Some(max_width) = self.max_width else {
+            return Cow::Borrowed(input);
+        };
+        let available = max_width.saturating_sub(used_width);
+        if input.chars().count() <= available {
+            return Cow::Borrowed(input);
+        }
+        let ellipsis = ellipsis(self.glyphs);
+        match available.checked_sub(ellipsis.chars().count()) {
+            Some(kept) => Cow::Owned(input.chars().take(kept).chain(ellipsis.chars()).collect()),
+            None => Cow::Borrowed(""),
+        }
+    }
+
+    /// Collect the additional details that are displayed for a report in verbose mode.
+    fn details(report: &RepositoryView) -> Vec<String> {
+        let mut details = vec![format!(
+            "M{} U{} S{}",
+            report.modified, report.untracked, report.staged
+        )];
+        if let Some(upstream) = &report.upstream {
+            details.push(format!("upstream:{upstream}"));
+        }
+        if let Some(describe) = &report.describe {
+            details.push(format!("describe:{describe}"));
+        }
+        if let Some(remote_default_branch) = &report.remote_default_branch {
+            details.push(format!("default:{remote_default_branch}"));
+        }
+        if report.lfs {
+            details.push("lfs".to_string());
+        }
+        if report.shallow {
+            details.push("shallow".to_string());
+        }
+        if let Some(size_bytes) = report.size_bytes {
+            details.push(format!("size:{}", format_size(size_bytes)));
+        }
+        let submodules = |filter: fn(&&SubmoduleView) -> bool| {
+            report
+                .submodules
+                .iter()
+                .filter(filter)
+                .map(|submodule| submodule.name.as_str())
+                .collect::<Vec<&str>>()
+                .join(",")
+        };
+        let out_of_sync = submodules(|submodule| submodule.out_of_sync);
+        if !out_of_sync.is_empty() {
+            details.push(format!("sub-out-of-sync:{out_of_sync}"));
+        }
+        let uninitialized = submodules(|submodule| !submodule.initialized);
+        if !uninitialized.is_empty() {
+            details.push(format!("sub-uninitialized:{uninitialized}"));
+        }
+        details
+    }
+
+    /// Display [`RepositoryCollection`] to `stdout` in JSON format. The reports are wrapped in an
+    /// object with the [`JSON_SCHEMA_VERSION`] (even if there are no reports) alongside the errors
+    /// recorded since the last display (if there is an error log).
+    fn json(&self, reports: &RepositoryCollection) -> Result<()> {
         debug!("detected json display mode");
         let mut all_reports = Vec::new();
         for grouped_report in reports {
             all_reports.append(&mut grouped_report.1.clone());
         }
-        all_reports.sort_by(|a, b| a.name.cmp(&b.name));
-        all_reports.sort_by(|a, b| a.status.as_str().cmp(b.status.as_str()));
-        println!("{}", serde_json::to_string_pretty(&all_reports)?);
+        self.sort(&mut all_reports, false);
+        let output = JsonOutput {
+            schema_version: JSON_SCHEMA_VERSION,
+            repositories: &all_reports,
+            errors: self
+                .error_log
+                .as_ref()
+                .map(ErrorLog::take)
+                .unwrap_or_default(),
+        };
+        writeln!(self.out(), "{}", serde_json::to_string_pretty(&output)?)?;
+        Ok(())
+    }
+
+    /// Display a single [`RepositoryView`] as one line of JSON. The line is written with a single
+    /// write (while holding the `stdout` lock, if writing to `stdout`) and flushed immediately,
+    /// which ensures that lines written from multiple threads do not interleave and that consumers
+    /// reading from a pipe see each line promptly.
+    pub fn ndjson(&self, report: &RepositoryView) -> Result<()> {
+        if self.summary_only {
+            return Ok(());
+        }
+        let mut line = serde_json::to_vec(report)?;
+        line.push(b'\n');
+        let mut out = self.out();
+        out.write_all(&line)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Display [`RepositoryCollection`] to `stdout` in the porcelain format: one line per report
+    /// with tab-separated columns. Missing values are displayed as empty strings in order to keep
+    /// the column positions fixed.
+    fn porcelain(&self, reports: &RepositoryCollection) -> Result<()> {
+        debug!("detected porcelain display mode");
+        let mut all_reports = Vec::new();
+        for grouped_report in reports {
+            all_reports.append(&mut grouped_report.1.clone());
+        }
+        self.sort(&mut all_reports, true);
+
+        let mut out = self.out();
+        for report in all_reports {
+            let full_path = match &report.parent {
+                Some(parent) => Path::new(parent).join(&report.name),
+                None => Path::new(&report.name).to_path_buf(),
+            };
+            write!(
+                out,
+                "{}\t{}\t{}\t{}\t{}{}",
+                full_path.display(),
+                report.status.as_str(),
+                report.branch,
+                report.url.as_deref().unwrap_or_default(),
+                report.email.as_deref().unwrap_or_default(),
+                self.record_terminator()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Display [`RepositoryCollection`] to `stdout` with each report rendered on its own line by
+    /// the [`Template`]. Reports are sorted like in the porcelain display mode.
+    fn template(&self, reports: &RepositoryCollection, template: &Template) -> Result<()> {
+        debug!("detected format template");
+        let mut all_reports = Vec::new();
+        for grouped_report in reports {
+            all_reports.append(&mut grouped_report.1.clone());
+        }
+        self.sort(&mut all_reports, true);
+
+        let mut out = self.out();
+        for report in all_reports {
+            write!(
+                out,
+                "{}{}",
+                template.render(&report),
+                self.record_terminator()
+            )?;
+        }
         Ok(())
     }
 
     /// Display [`RepositoryCollection`] to `stdout` in the classic format.
-    fn classic(reports: &RepositoryCollection, color_mode: ColorMode) -> io::Result<()> {
+    fn classic(&self, reports: &RepositoryCollection) -> io::Result<()> {
         debug!("detected classic display mode");
-        let color_harness = ColorHarness::new(color_mode);
+        let color_harness = ColorHarness::new(self.color_mode, self.palette, self.output.clone());
 
         let length = reports.keys().len();
         let mut first = true;
@@ -117,7 +716,7 @@ impl DisplayHarness {
                 if first {
                     first = false;
                 } else {
-                    println!();
+                    writeln!(self.out())?;
                 }
                 color_harness.write_bold(
                     match &title {
@@ -132,8 +731,9 @@ impl DisplayHarness {
             let mut branch_max = 0;
             let mut status_max = 0;
             for report in group {
-                if report.name.len() > name_max {
-                    name_max = report.name.len();
+                let name_length = self.name(report).chars().count();
+                if name_length > name_max {
+                    name_max = name_length;
                 }
                 let status_length = report.status.as_str().len();
                 if status_length > status_max {
@@ -145,23 +745,164 @@ impl DisplayHarness {
             }
 
             let mut reports = group.clone();
-            reports.sort_by(|a, b| a.name.cmp(&b.name));
-            reports.sort_by(|a, b| a.status.as_str().cmp(b.status.as_str()));
+            self.sort(&mut reports, false);
 
             for report in reports {
-                print!("{:<path_width$}", report.name, path_width = name_max + PAD);
+                write!(
+                    self.out(),
+                    "{:<path_width$}",
+                    self.name(&report),
+                    path_width = name_max + PAD
+                )?;
                 color_harness.write_status(report.status, status_max + PAD)?;
-                println!(
-                    "{:<branch_width$}{}",
-                    report.branch,
+                color_harness.write_branch(
+                    &format!(
+                        "{:<branch_width$}",
+                        report.branch,
+                        branch_width = branch_max + PAD
+                    ),
+                    self.is_primary(&report.branch),
+                )?;
+                writeln!(
+                    self.out(),
+                    "{}",
                     match &report.url {
                         Some(s) => s,
                         None => NONE,
-                    },
-                    branch_width = branch_max + PAD
-                );
+                    }
+                )?;
             }
         }
         Ok(())
     }
+
+    /// Display [`RepositoryCollection`] to `stdout` in the compact format: one line per report with
+    /// its name, status glyph, branch and url aligned in columns across all groups. Reports are
+    /// sorted like in the standard display mode.
+    fn compact(&self, reports: &RepositoryCollection) -> io::Result<()> {
+        debug!("detected compact display mode");
+        let mut all_reports = Vec::new();
+        for grouped_report in reports {
+            all_reports.append(&mut grouped_report.1.clone());
+        }
+        self.sort(&mut all_reports, false);
+
+        let color_harness = ColorHarness::new(self.color_mode, self.palette, self.output.clone());
+        let name_max = all_reports
+            .iter()
+            .map(|report| self.name(report).chars().count())
+            .max()
+            .unwrap_or_default();
+        let branch_max = all_reports
+            .iter()
+            .map(|report| report.branch.chars().count())
+            .max()
+            .unwrap_or_default();
+
+        for report in all_reports {
+            write!(
+                self.out(),
+                "{:<name_width$}",
+                self.name(&report),
+                name_width = name_max + PAD
+            )?;
+            color_harness.write_status_text(
+                report.status,
+                glyph(report.status, self.glyphs),
+                1 + PAD,
+            )?;
+            color_harness.write_branch(
+                &format!(
+                    "{:<branch_width$}",
+                    report.branch,
+                    branch_width = branch_max + PAD
+                ),
+                self.is_primary(&report.branch),
+            )?;
+            // Emoji are a single character, but they are displayed two columns wide.
+            let glyph_width = match self.glyphs {
+                GlyphSet::Emoji => 2,
+                GlyphSet::Ascii | GlyphSet::Unicode => 1,
+            };
+            let used_width = name_max + PAD + glyph_width + PAD + branch_max + PAD;
+            writeln!(
+                self.out(),
+                "{}",
+                self.truncate(report.url.as_deref().unwrap_or(NONE), used_width)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The single-character glyph that represents a [`Status`] in the compact display mode.
+fn glyph(status: Status, glyphs: GlyphSet) -> &'static str {
+    match glyphs {
+        GlyphSet::Ascii => match status {
+            Status::Bare => "o",
+            Status::Clean => "=",
+            Status::Conflicted => "!",
+            Status::Diverged => "~",
+            Status::Unclean => "*",
+            Status::Unknown => "?",
+            Status::Unpulled => "-",
+            Status::Unpushed => "+",
+        },
+        GlyphSet::Emoji => match status {
+            Status::Bare => "📦",
+            Status::Clean => "✅",
+            Status::Conflicted => "💥",
+            Status::Diverged => "🔀",
+            Status::Unclean => "📝",
+            Status::Unknown => "❓",
+            Status::Unpulled => "📥",
+            Status::Unpushed => "📤",
+        },
+        GlyphSet::Unicode => match status {
+            Status::Bare => "○",
+            Status::Clean => "✓",
+            Status::Conflicted => "✗",
+            Status::Diverged => "↕",
+            Status::Unclean => "•",
+            Status::Unknown => "?",
+            Status::Unpulled => "↓",
+            Status::Unpushed => "↑",
+        },
+    }
+}
+
+/// The glyphs that prefix the ahead and behind counts, respectively. Emoji arrows are not used
+/// since they are hard to read next to the counts.
+fn arrows(glyphs: GlyphSet) -> (&'static str, &'static str) {
+    match glyphs {
+        GlyphSet::Ascii => ("+", "-"),
+        GlyphSet::Emoji | GlyphSet::Unicode => ("↑", "↓"),
+    }
+}
+
+/// The glyph that marks truncated text.
+fn ellipsis(glyphs: GlyphSet) -> &'static str {
+    match glyphs {
+        GlyphSet::Ascii => "...",
+        GlyphSet::Emoji | GlyphSet::Unicode => "…",
+    }
+}
+
+/// Format a size in bytes with the largest decimal unit (e.g. "MB") in which it is at least one,
+/// using one decimal place for units larger than bytes.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["kB", "MB", "GB", "TB", "PB"];
+    if bytes < 1000 {
+        return format!("{bytes}B");
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if size < 1000.0 {
+            break;
+        }
+        size /= 1000.0;
+        unit = next;
+    }
+    format!("{size:.1}{unit}")
 }
@@ -1,55 +1,114 @@
-//! This module provides a harness for non-trivial displays of information to `stdout`.
+//! This module provides a harness for non-trivial displays of information to the output.
 
+use std::fs::File;
 use std::io;
 use std::io::Write;
-use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+use std::sync::Arc;
 
-use crate::{config::ColorMode, status::Status};
+use anyhow::{Result, anyhow};
+use termcolor::{Ansi, Color, ColorChoice, ColorSpec, NoColor, StandardStream, WriteColor};
 
-/// This harness provides methods to write to `stdout`. It maps the internal [`ColorMode`] type to
-/// our dependency's [`ColorChoice`] type due to discrepancies in behavior and naming.
+use crate::config::{ColorMode, StatusColors};
+use crate::status::Status;
+
+/// Where displayed results are written to.
+#[remain::sorted]
+#[derive(Debug, Clone, Default)]
+pub enum Output {
+    /// Write to the file (e.g. for snapshots of the results).
+    File(Arc<File>),
+    /// Write to the output (default behavior).
+    #[default]
+    Stdout,
+}
+
+impl Output {
+    /// Get a writer for the output. Writes to a file are unbuffered, so writes from separate
+    /// writers are never reordered.
+    pub fn writer(&self) -> Box<dyn Write + '_> {
+        match self {
+            Self::File(file) => Box::new(file.as_ref()),
+            Self::Stdout => Box::new(io::stdout()),
+        }
+    }
+}
+
+/// This harness provides methods to write to the [`Output`]. It maps the internal [`ColorMode`]
+/// type to our dependency's [`ColorChoice`] type due to discrepancies in behavior and naming.
 #[derive(Debug)]
 pub struct ColorHarness {
     color_choice: ColorChoice,
+    palette: StatusPalette,
+    output: Output,
 }
 
 impl ColorHarness {
-    /// Creates a new color harness.
-    pub fn new(color_mode: ColorMode) -> Self {
+    /// Creates a new color harness, which writes statuses with the colors from the palette.
+    pub fn new(color_mode: ColorMode, palette: StatusPalette, output: Output) -> Self {
         Self {
             color_choice: match &color_mode {
                 ColorMode::Always => ColorChoice::Always,
                 ColorMode::Compatibility => ColorChoice::Auto,
                 ColorMode::Never => ColorChoice::Never,
             },
+            palette,
+            output,
+        }
+    }
+
+    /// Open a stream to the output. Files only receive colors if they are always displayed since
+    /// compatibility mode only displays colors to terminals.
+    fn stream(&self) -> Box<dyn WriteColor + '_> {
+        match (&self.output, self.color_choice) {
+            (Output::File(file), ColorChoice::Always) => Box::new(Ansi::new(file.as_ref())),
+            (Output::File(file), _) => Box::new(NoColor::new(file.as_ref())),
+            (Output::Stdout, color_choice) => Box::new(StandardStream::stdout(color_choice)),
         }
     }
 
-    /// Writes the [`Status`] of the Git repository to `stdout`.
+    /// Writes the [`Status`] of the Git repository to the output.
     pub fn write_status(&self, status: Status, status_width: usize) -> io::Result<()> {
-        let mut stdout = StandardStream::stdout(self.color_choice);
-        stdout.set_color(ColorSpec::new().set_fg(Some(match status {
-            Status::Bare | Status::Unknown => Color::Red,
-            Status::Clean => Color::Green,
-            Status::Unpushed => Color::Blue,
-            Status::Unclean => Color::Yellow,
-            Status::Unpulled => Color::Magenta,
-        })))?;
+        self.write_status_text(status, status.as_str(), status_width)
+    }
+
+    /// Writes the text (e.g. a glyph) in the color of the [`Status`] to the output.
+    pub fn write_status_text(
+        &self,
+        status: Status,
+        text: &str,
+        status_width: usize,
+    ) -> io::Result<()> {
+        let mut stream = self.stream();
+        stream.set_color(&self.palette.color_spec(status))?;
         write!(
-            &mut stdout,
+            &mut stream,
             "{:<status_width$}",
-            status.as_str(),
+            text,
             status_width = status_width,
         )?;
-        stdout.reset()
+        stream.reset()
     }
 
-    /// Writes the input [`&str`] to `stdout` in bold.
+    /// Writes the branch to the output, which is highlighted if it is not a primary branch (e.g. if
+    /// a feature branch was left checked out). The highlight is bold and underlined rather than a
+    /// color, so that it remains distinguishable next to any status color.
+    pub fn write_branch(&self, branch: &str, primary: bool) -> io::Result<()> {
+        match primary {
+            true => write!(self.stream(), "{branch}"),
+            false => self.write_color(
+                branch,
+                false,
+                ColorSpec::new().set_bold(true).set_underline(true),
+            ),
+        }
+    }
+
+    /// Writes the input [`&str`] to the output in bold.
     pub fn write_bold(&self, input: &str, newline: bool) -> io::Result<()> {
         self.write_color(input, newline, ColorSpec::new().set_bold(true))
     }
 
-    /// Writes the input [`&str`] to `stdout` in gray (or cyan if in compatibility mode).
+    /// Writes the input [`&str`] to the output in gray (or cyan if in compatibility mode).
     pub fn write_gray(&self, input: &str, newline: bool) -> io::Result<()> {
         // FIXME(nick): check why Color::Rg(128, 128, 128) breaks in tmux on macOS Terminal.app.
         self.write_color(
@@ -68,13 +127,104 @@ impl ColorHarness {
         newline: bool,
         color_spec: &mut ColorSpec,
     ) -> io::Result<()> {
-        let mut stdout = StandardStream::stdout(self.color_choice);
-        stdout.set_color(color_spec)?;
+        let mut stream = self.stream();
+        stream.set_color(color_spec)?;
         if newline {
-            writeln!(&mut stdout, "{input}")?;
+            writeln!(&mut stream, "{input}")?;
         } else {
-            write!(&mut stdout, "{input}")?;
+            write!(&mut stream, "{input}")?;
+        }
+        stream.reset()
+    }
+}
+
+/// The colors that each [`Status`] is written with.
+#[derive(Debug, Clone, Copy)]
+pub struct StatusPalette {
+    bare: Color,
+    clean: Color,
+    conflicted: Color,
+    diverged: Color,
+    unclean: Color,
+    unknown: Color,
+    unpulled: Color,
+    unpushed: Color,
+}
+
+impl Default for StatusPalette {
+    fn default() -> Self {
+        Self {
+            bare: Color::Red,
+            clean: Color::Green,
+            conflicted: Color::Red,
+            diverged: Color::Cyan,
+            unclean: Color::Yellow,
+            unknown: Color::Red,
+            unpulled: Color::Magenta,
+            unpushed: Color::Blue,
+        }
+    }
+}
+
+impl StatusPalette {
+    /// Create a palette from the configured [`StatusColors`], falling back to the default color for
+    /// each status without one. An error is returned if a color is invalid.
+    pub fn new(colors: &StatusColors) -> Result<Self> {
+        let defaults = Self::default();
+        let parse = |color: &Option<String>, status: Status, default: Color| match color {
+            Some(color) => parse_color(color)
+                .map_err(|e| anyhow!("invalid color for status ({}): {e}", status.as_str())),
+            None => Ok(default),
+        };
+        Ok(Self {
+            bare: parse(&colors.bare, Status::Bare, defaults.bare)?,
+            clean: parse(&colors.clean, Status::Clean, defaults.clean)?,
+            conflicted: parse(&colors.conflicted, Status::Conflicted, defaults.conflicted)?,
+            diverged: parse(&colors.diverged, Status::Diverged, defaults.diverged)?,
+            unclean: parse(&colors.unclean, Status::Unclean, defaults.unclean)?,
+            unknown: parse(&colors.unknown, Status::Unknown, defaults.unknown)?,
+            unpulled: parse(&colors.unpulled, Status::Unpulled, defaults.unpulled)?,
+            unpushed: parse(&colors.unpushed, Status::Unpushed, defaults.unpushed)?,
+        })
+    }
+
+    /// Get the color spec that the [`Status`] is written with.
+    pub fn color_spec(&self, status: Status) -> ColorSpec {
+        let mut color_spec = ColorSpec::new();
+        color_spec.set_fg(Some(self.color(status)));
+        // Conflicts usually mean that a merge was left unfinished, so they stand out the most.
+        if status == Status::Conflicted {
+            color_spec.set_bold(true).set_intense(true);
         }
-        stdout.reset()
+        color_spec
+    }
+
+    fn color(&self, status: Status) -> Color {
+        match status {
+            Status::Bare => self.bare,
+            Status::Clean => self.clean,
+            Status::Conflicted => self.conflicted,
+            Status::Diverged => self.diverged,
+            Status::Unclean => self.unclean,
+            Status::Unknown => self.unknown,
+            Status::Unpulled => self.unpulled,
+            Status::Unpushed => self.unpushed,
+        }
+    }
+}
+
+/// Parse a color name, ANSI 256 color number, RGB triple or hex value (e.g. "#ff8000").
+fn parse_color(color: &str) -> Result<Color> {
+    let color = color.trim();
+    if let Some(hex) = color.strip_prefix('#') {
+        let channel = |range| {
+            hex.get(range)
+                .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+        };
+        return match (hex.len(), channel(0..2), channel(2..4), channel(4..6)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb(r, g, b)),
+            _ => Err(anyhow!("invalid hex color: {color}")),
+        };
     }
+    color.parse().map_err(|e| anyhow!("{e}"))
 }
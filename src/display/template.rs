@@ -0,0 +1,139 @@
+//! This module contains [`Template`], which renders each report on a single line using a
+//! user-provided format.
+
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+
+use crate::repository_view::RepositoryView;
+
+/// The placeholders that can be used in a [`Template`].
+const PLACEHOLDERS: &[&str] = &[
+    "ahead", "behind", "branch", "email", "name", "parent", "path", "stashes", "status",
+    "upstream", "url",
+];
+
+/// A parsed output template. Placeholders are written as `{name}` (see [`PLACEHOLDERS`]) and
+/// literal braces are written as `{{` and `}}`. Placeholders for missing values (e.g. a repository
+/// without a remote URL) render as empty strings.
+#[derive(Debug, Clone)]
+pub(crate) struct Template {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Placeholder(&'static str),
+}
+
+impl Template {
+    /// Parse a template. An error is returned if the template contains an unknown or unclosed
+    /// placeholder.
+    pub(crate) fn parse(template: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.next_if_eq(&'{').is_some() => literal.push('{'),
+                '}' if chars.next_if_eq(&'}').is_some() => literal.push('}'),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => {
+                                return Err(anyhow!("unclosed placeholder in format: {template}"));
+                            }
+                        }
+                    }
+                    let Some(placeholder) = PLACEHOLDERS.iter().find(|p| **p == name) else {
+                        return Err(anyhow!(
+                            "unknown placeholder ({{{name}}}) in format (expected one of: {}): {template}",
+                            PLACEHOLDERS.join(", ")
+                        ));
+                    };
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Placeholder(placeholder));
+                }
+                '}' => return Err(anyhow!("unmatched '}}' in format: {template}")),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Self { segments })
+    }
+
+    /// Render the template for a report (without a trailing newline).
+    pub(crate) fn render(&self, report: &RepositoryView) -> String {
+        let mut rendered = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(literal) => rendered.push_str(literal),
+                Segment::Placeholder(placeholder) => {
+                    rendered.push_str(&Self::value(placeholder, report))
+                }
+            }
+        }
+        rendered
+    }
+
+    fn value(placeholder: &str, report: &RepositoryView) -> String {
+        match placeholder {
+            "ahead" => report.ahead.to_string(),
+            "behind" => report.behind.to_string(),
+            "branch" => report.branch.clone(),
+            "email" => report.email.clone().unwrap_or_default(),
+            "name" => report.name.clone(),
+            "parent" => report.parent.clone().unwrap_or_default(),
+            "path" => match &report.parent {
+                Some(parent) => Path::new(parent).join(&report.name).display().to_string(),
+                None => report.name.clone(),
+            },
+            "stashes" => report.stashes.to_string(),
+            "status" => report.status.as_str().to_string(),
+            "upstream" => report.upstream.clone().unwrap_or_default(),
+            "url" => report.url.clone().unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::Status;
+    use pretty_assertions::assert_eq;
+
+    #[allow(clippy::panic_in_result_fn)]
+    #[test]
+    fn template_rendering() -> Result<()> {
+        let mut report = RepositoryView::finalize(
+            Path::new("/src/gfold"),
+            Some("main".to_string()),
+            Status::Unpushed,
+            None,
+            Some("nick@example.com".to_string()),
+            Vec::new(),
+        )?;
+        report.ahead = 2;
+
+        let template = Template::parse("{name} {{{branch}}} {status}:+{ahead}-{behind} [{url}]")?;
+        assert_eq!(template.render(&report), "gfold {main} unpushed:+2-0 []");
+        assert_eq!(
+            Template::parse("{path}\t{email}")?.render(&report),
+            "/src/gfold\tnick@example.com"
+        );
+
+        assert!(Template::parse("{nope}").is_err());
+        assert!(Template::parse("{name").is_err());
+        assert!(Template::parse("name}").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+//! This module contains the functionality for detecting the width of the terminal.
+
+use std::env;
+
+/// Find the width of the terminal that `stdout` is attached to, falling back to the "COLUMNS"
+/// environment variable.
+pub(crate) fn width() -> Option<usize> {
+    window_width()
+        .or_else(|| env::var("COLUMNS").ok()?.parse().ok())
+        .filter(|width| *width > 0)
+}
+
+#[cfg(unix)]
+fn window_width() -> Option<usize> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: "TIOCGWINSZ" only writes to the provided "winsize", which outlives the call.
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    (result == 0 && size.ws_col > 0).then_some(usize::from(size.ws_col))
+}
+
+#[cfg(not(unix))]
+fn window_width() -> Option<usize> {
+    None
+}
@@ -0,0 +1,248 @@
+//! This module contains [`Glob`], which is used to match paths against user-provided patterns.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use log::{debug, trace};
+
+/// A compiled glob pattern. Patterns support the following syntax:
+///
+/// - `*` matches any sequence of characters except for `/`
+/// - `**` matches any sequence of characters, including `/`
+/// - `?` matches any single character except for `/`
+/// - `[abc]`, `[a-z]` and `[!abc]` match (or do not match) a single character from the class
+///
+/// Patterns containing a `/` are matched against the full path. Patterns without a `/` are
+/// matched against the final component of the path (e.g. `node_modules` matches any directory
+/// with that name).
+#[derive(Debug, Clone)]
+pub struct Glob {
+    pattern: String,
+    tokens: Vec<Token>,
+    file_name_only: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    Literal(char),
+    AnyChar,
+    Star,
+    DoubleStar,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Glob {
+    /// Compile a glob pattern. An error is returned if the pattern contains an unclosed character
+    /// class.
+    pub fn new(pattern: &str) -> Result<Self> {
+        let mut tokens = Vec::new();
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            tokens.push(match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    Token::DoubleStar
+                }
+                '*' => Token::Star,
+                '?' => Token::AnyChar,
+                '[' => {
+                    let negated = chars.next_if(|c| *c == '!' || *c == '^').is_some();
+                    let mut ranges = Vec::new();
+                    loop {
+                        match chars.next() {
+                            Some(']') if !ranges.is_empty() => break,
+                            Some(start) => match chars.next_if_eq(&'-') {
+                                Some(_) => match chars.next() {
+                                    Some(']') => {
+                                        ranges.push((start, start));
+                                        ranges.push(('-', '-'));
+                                        break;
+                                    }
+                                    Some(end) => ranges.push((start, end)),
+                                    None => break,
+                                },
+                                None => ranges.push((start, start)),
+                            },
+                            None => {
+                                return Err(anyhow!(
+                                    "unclosed character class in glob pattern: {pattern}"
+                                ));
+                            }
+                        }
+                    }
+                    Token::Class { negated, ranges }
+                }
+                c => Token::Literal(c),
+            });
+        }
+        Ok(Self {
+            pattern: pattern.to_string(),
+            tokens,
+            file_name_only: !pattern.contains('/'),
+        })
+    }
+
+    /// The pattern that the glob was compiled from.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Check if the input contains any glob metacharacters (i.e. it should be compiled as a pattern
+    /// rather than used as a literal path).
+    pub fn is_pattern(input: &str) -> bool {
+        input.contains(['*', '?', '['])
+    }
+
+    /// Expand a pattern to the directories that match it, sorted by path. Each component of the
+    /// path that contains metacharacters is matched against the entries of the directories found
+    /// so far (i.e. there is no recursive traversal and `**` only matches a single level). Hidden
+    /// entries only match components that start with a dot. Non-directory matches and unreadable
+    /// directories are skipped.
+    pub fn expand(pattern: &Path) -> Result<Vec<PathBuf>> {
+        let mut candidates = vec![PathBuf::new()];
+        for component in pattern.components() {
+            let name = match component {
+                Component::Normal(name) => name.to_str().filter(|name| Self::is_pattern(name)),
+                _ => None,
+            };
+            let Some(name) = name else {
+                for candidate in &mut candidates {
+                    candidate.push(component);
+                }
+                continue;
+            };
+            let glob = Self::new(name)?;
+            let mut matches = Vec::new();
+            for candidate in &candidates {
+                let parent = match candidate.as_os_str().is_empty() {
+                    true => Path::new("."),
+                    false => candidate.as_path(),
+                };
+                let entries = match fs::read_dir(parent) {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        trace!("ignored error: {e}");
+                        continue;
+                    }
+                };
+                for entry in entries.flatten() {
+                    let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                    let path = candidate.join(entry.file_name());
+                    if (!hidden || name.starts_with('.')) && glob.is_match(&path) && path.is_dir() {
+                        matches.push(path);
+                    }
+                }
+            }
+            candidates = matches;
+        }
+        candidates.retain(|candidate| candidate.is_dir());
+        candidates.sort();
+        debug!("expanded pattern ({}): {candidates:?}", pattern.display());
+        Ok(candidates)
+    }
+
+    /// Check if the path matches the pattern. Paths are matched with `/` as the separator on all
+    /// platforms. Directory paths also match patterns ending in `/**` (i.e. the directory itself
+    /// matches, not only its descendants).
+    pub fn is_match(&self, path: &Path) -> bool {
+        if self.file_name_only {
+            return path
+                .file_name()
+                .is_some_and(|name| self.matches(&name.to_string_lossy()));
+        }
+        let path = path.to_string_lossy().replace('\\', "/");
+        self.matches(&path) || self.matches(&format!("{path}/"))
+    }
+
+    /// Match the input against the tokens using dynamic programming, where `table[t][i]` is true
+    /// when the tokens from `t` onwards match the input from `i` onwards.
+    fn matches(&self, input: &str) -> bool {
+        let input: Vec<char> = input.chars().collect();
+        let (token_count, input_len) = (self.tokens.len(), input.len());
+        let mut table = vec![vec![false; input_len + 1]; token_count + 1];
+        table[token_count][input_len] = true;
+
+        for t in (0..token_count).rev() {
+            for i in (0..=input_len).rev() {
+                let current = input.get(i).copied();
+                table[t][i] = match &self.tokens[t] {
+                    Token::Literal(c) => current == Some(*c) && table[t + 1][i + 1],
+                    Token::AnyChar => current.is_some_and(|c| c != '/') && table[t + 1][i + 1],
+                    Token::Class { negated, ranges } => {
+                        current.is_some_and(|c| {
+                            let in_class = ranges.iter().any(|(s, e)| (*s..=*e).contains(&c));
+                            c != '/' && in_class != *negated
+                        }) && table[t + 1][i + 1]
+                    }
+                    Token::Star => {
+                        table[t + 1][i] || (current.is_some_and(|c| c != '/') && table[t][i + 1])
+                    }
+                    Token::DoubleStar => table[t + 1][i] || (current.is_some() && table[t][i + 1]),
+                };
+            }
+        }
+        table[0][0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[allow(clippy::panic_in_result_fn)]
+    #[test]
+    fn glob_matching() -> Result<()> {
+        let node_modules = Glob::new("**/node_modules/**")?;
+        assert!(node_modules.is_match(Path::new("/home/neloth/src/node_modules")));
+        assert!(node_modules.is_match(Path::new("/home/neloth/src/node_modules/left-pad")));
+        assert!(!node_modules.is_match(Path::new("/home/neloth/src/node_modules_backup")));
+
+        let file_name = Glob::new("vendor*")?;
+        assert!(file_name.is_match(Path::new("/home/neloth/src/vendored")));
+        assert!(!file_name.is_match(Path::new("/home/neloth/vendor/src")));
+
+        let single_level = Glob::new("/home/*/src/[a-c]?")?;
+        assert!(single_level.is_match(Path::new("/home/neloth/src/b1")));
+        assert!(!single_level.is_match(Path::new("/home/neloth/nested/src/b1")));
+        assert!(!single_level.is_match(Path::new("/home/neloth/src/d1")));
+
+        let negated = Glob::new("/tmp/[!x]")?;
+        assert!(negated.is_match(Path::new("/tmp/y")));
+        assert!(!negated.is_match(Path::new("/tmp/x")));
+
+        assert!(Glob::new("/tmp/[abc").is_err());
+        Ok(())
+    }
+
+    #[allow(clippy::panic_in_result_fn)]
+    #[test]
+    fn glob_expansion() -> Result<()> {
+        let root = tempfile::tempdir()?;
+        for dir in ["a/repo", "b/repo", "b/other", "c", ".d/repo"] {
+            fs::create_dir_all(root.path().join(dir))?;
+        }
+        fs::write(root.path().join("e"), "")?;
+
+        let expanded = Glob::expand(&root.path().join("*").join("repo"))?;
+        assert_eq!(
+            expanded,
+            vec![root.path().join("a/repo"), root.path().join("b/repo")]
+        );
+        let expanded = Glob::expand(&root.path().join("?"))?;
+        assert_eq!(
+            expanded,
+            vec![
+                root.path().join("a"),
+                root.path().join("b"),
+                root.path().join("c")
+            ]
+        );
+        assert!(Glob::expand(&root.path().join("x*"))?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+//! [gfold](https://github.com/nickgerace/gfold) is a CLI tool that helps you keep track of
+//! multiple Git repositories. This library provides the collection of results for repositories
+//! without going through the CLI (e.g. for embedding in other tools), starting with [`collect()`].
+
+#![warn(
+    bad_style,
+    clippy::missing_panics_doc,
+    clippy::panic,
+    clippy::panic_in_result_fn,
+    clippy::unwrap_in_result,
+    clippy::unwrap_used,
+    dead_code,
+    improper_ctypes,
+    missing_debug_implementations,
+    // TODO(nick): fix missing docs.
+    // missing_docs,
+    no_mangle_generic_items,
+    non_shorthand_field_patterns,
+    overflowing_literals,
+    path_statements,
+    patterns_in_fns_without_body,
+    unconditional_recursion,
+    unreachable_pub,
+    unused,
+    unused_allocation,
+    unused_comparisons,
+    unused_parens,
+    while_true
+)]
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::collector::{CollectOptions, RepositoryCollector, SeenRepositories};
+use crate::repository_view::RepositoryView;
+
+pub mod bundle_view;
+pub mod collector;
+pub mod config;
+pub mod display;
+pub mod glob;
+pub mod logging;
+mod regex;
+pub mod repository_view;
+pub mod status;
+
+/// Collect a [`RepositoryView`] for each repository found in the given paths and their children,
+/// sorted by parent directory and then by name for each path. Repositories found more than once
+/// (e.g. through overlapping paths) are only included the first time (see [`SeenRepositories`]).
+/// Bundles are never collected. This is the equivalent of running the CLI without displaying the
+/// results.
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// use gfld::collector::CollectOptions;
+///
+/// let options = CollectOptions {
+///     include_email: true,
+///     ..Default::default()
+/// };
+/// for view in gfld::collect(&[PathBuf::from("/home/neloth/src")], &options)? {
+///     println!("{} {}", view.name, view.status.as_str());
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn collect(paths: &[PathBuf], options: &CollectOptions) -> Result<Vec<RepositoryView>> {
+    let mut views = Vec::new();
+    let seen = SeenRepositories::default();
+    for path in paths {
+        let (collection, _) = RepositoryCollector::run(path, options, &seen)?;
+        views.extend(collection.into_values().flatten());
+    }
+    Ok(views)
+}
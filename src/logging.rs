@@ -0,0 +1,78 @@
+//! This module contains the functionality for initializing the logger, including logging to a
+//! file with the repository being collected in each line.
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use env_logger::Target;
+use log::LevelFilter;
+
+thread_local! {
+    /// The repository being collected on the current thread (if any).
+    static REPOSITORY: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Initialize the logger. Logs are written to `stderr` unless a log file is provided, in which
+/// case logs (at least up to the "debug" level) are written to the file instead. Lines in the log
+/// file include the repository being collected when the line was logged.
+pub fn init(level: LevelFilter, log_file: Option<&Path>) -> Result<()> {
+    let mut builder = env_logger::Builder::new();
+    match log_file {
+        Some(log_file) => {
+            let file = File::create(log_file)
+                .with_context(|| format!("could not create log file: {}", log_file.display()))?;
+            // The logger synchronizes writes to the target, so lines from parallel collection
+            // are not interleaved.
+            builder
+                .filter_level(level.max(LevelFilter::Debug))
+                .target(Target::Pipe(Box::new(file)))
+                .format(|buf, record| {
+                    let timestamp = buf.timestamp();
+                    REPOSITORY.with_borrow(|repository| match repository {
+                        Some(repository) => writeln!(
+                            buf,
+                            "[{timestamp} {:<5} {}] ({repository}) {}",
+                            record.level(),
+                            record.target(),
+                            record.args()
+                        ),
+                        None => writeln!(
+                            buf,
+                            "[{timestamp} {:<5} {}] {}",
+                            record.level(),
+                            record.target(),
+                            record.args()
+                        ),
+                    })
+                });
+        }
+        None => {
+            builder.filter_level(level);
+        }
+    }
+    builder.init();
+    Ok(())
+}
+
+/// Indicates that a repository is being collected on the current thread until dropped.
+#[derive(Debug)]
+pub struct RepositoryScope {
+    previous: Option<String>,
+}
+
+impl RepositoryScope {
+    /// Enter the scope for the repository at the given path.
+    pub fn enter(path: &Path) -> Self {
+        let previous = REPOSITORY.replace(Some(path.display().to_string()));
+        Self { previous }
+    }
+}
+
+impl Drop for RepositoryScope {
+    fn drop(&mut self) {
+        REPOSITORY.set(self.previous.take());
+    }
+}
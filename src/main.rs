@@ -27,38 +27,117 @@
     while_true
 )]
 
-use std::{env, path::PathBuf};
-
-use anyhow::Result;
-use args::Cli;
-use clap::Parser;
-use collector::RepositoryCollector;
-use log::debug;
-
-use crate::config::{Config, DisplayMode};
-use crate::display::DisplayHarness;
-
-// TODO(nick): investigate module visibility.
-pub mod args;
-pub mod collector;
-pub mod config;
-pub mod display;
-pub mod repository_view;
-pub mod status;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use args::{Cli, Command, DryRun, UrlScheme};
+use clap::{CommandFactory, Parser};
+use gfld::collector::{
+    CollectOptions, EmailAllowlist, RepositoryCollection, RepositoryCollector, SeenRepositories,
+    SkippedDirectories, Transport,
+};
+use gfld::config::{self, ColorMode, Config, DisplayMode};
+use gfld::display::DisplayHarness;
+use gfld::display::color::StatusPalette;
+use gfld::glob::Glob;
+use gfld::logging;
+use gfld::repository_view::RepositoryView;
+use gfld::repository_view::credentials;
+use gfld::repository_view::error_log::ErrorLog;
+use gfld::repository_view::fetch_cache::FetchCache;
+use gfld::repository_view::fetch_limiter::FetchLimiter;
+use gfld::repository_view::origin_head::OriginHeadLog;
+use gfld::repository_view::passphrase::PassphrasePrompt;
+use gfld::repository_view::profile::Profile;
+use gfld::repository_view::status_cache::StatusCache;
+use gfld::status::Status;
+use log::{debug, warn};
+
+// Modules that are only used by the CLI (the rest of the modules are in the library).
+mod args;
+mod completions;
+mod man;
+mod tui;
+mod urls;
+mod watch;
+
+/// The exit code used with `--exit-code` when at least one repository needs attention.
+const EXIT_CODE_NEEDS_ATTENTION: u8 = 1;
+/// The exit code used with `--exit-code` when an error occurs.
+const EXIT_CODE_ERROR: u8 = 2;
+/// The number of threads used for remote fetches if not provided. Fetches mostly wait on the
+/// network, so this is higher than the number of logical CPUs on most machines.
+const DEFAULT_FETCH_THREADS: usize = 16;
+/// The number of repositories displayed with `--profile`.
+const PROFILE_SLOWEST_COUNT: usize = 5;
+
+/// Parses CLI arguments and runs the application. Errors are printed the same way as they would
+/// be when returned from `main`. If `--exit-code` is provided, the exit code reflects whether or
+/// not any repository needs attention (i.e. its status is not "clean").
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let use_exit_code = cli.exit_code;
+    match run(cli) {
+        Ok(true) if use_exit_code => ExitCode::from(EXIT_CODE_NEEDS_ATTENTION),
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            match use_exit_code {
+                true => ExitCode::from(EXIT_CODE_ERROR),
+                false => ExitCode::FAILURE,
+            }
+        }
+    }
+}
 
 /// Initializes the logger based on the debug flag and `RUST_LOG` environment variable, then
-/// parses CLI arguments and generates a [`Config`] by merging configurations as needed,
-/// and finally collects results and displays them.
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+/// generates a [`Config`] by merging configurations as needed, and finally collects results and
+/// displays them. Returns whether or not any collected repository needs attention.
+fn run(cli: Cli) -> Result<bool> {
+    let mut needs_attention = false;
 
-    env_logger::Builder::new()
-        .filter_level(cli.verbose.log_level_filter())
-        .init();
+    logging::init(cli.verbose.log_level_filter(), cli.log_file.as_deref())?;
     debug!("initialized logger");
 
+    match &cli.command {
+        Some(Command::Completions { shell }) => {
+            completions::generate(*shell, &Cli::command(), &mut io::stdout().lock())?;
+            return Ok(needs_attention);
+        }
+        Some(Command::Man { output_dir }) => {
+            let command = Cli::command();
+            match output_dir {
+                Some(output_dir) => {
+                    let path = output_dir.join(format!("{}.1", command.get_name()));
+                    let mut file = File::create(&path).with_context(|| {
+                        format!("could not create man page: {}", path.display())
+                    })?;
+                    man::generate(&command, &mut file)?;
+                }
+                None => man::generate(&command, &mut io::stdout().lock())?,
+            }
+            return Ok(needs_attention);
+        }
+        None => {}
+    }
+
+    // A value of zero tells rayon to use its default, which is the number of logical CPUs.
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(cli.jobs.unwrap_or(0))
+        .build_global()?;
+    debug!("initialized thread pool");
+
     let mut config = if cli.ignore_config_file {
         Config::try_config_default()?
+    } else if let Some(found_config) = &cli.config {
+        Config::try_config_file(found_config)?
     } else {
         Config::try_config()?
     };
@@ -67,55 +146,483 @@ fn main() -> Result<()> {
     if let Some(found_display_mode_raw) = &cli.display_mode {
         config.display_mode = *found_display_mode_raw;
     }
+    // Color mode precedence: CLI flags, then the "NO_COLOR" environment variable, then the config
+    // file (or its default).
     if let Some(found_color_mode) = &cli.color_mode {
         config.color_mode = *found_color_mode;
+    } else if cli.no_color || env::var_os("NO_COLOR").is_some() {
+        config.color_mode = ColorMode::Never;
+    }
+    if let Some(found_glyphs) = cli.glyphs {
+        config.glyphs = found_glyphs;
+    }
+    if let Some(found_sort_by) = cli.sort_by {
+        config.sort_by = Some(found_sort_by);
+    }
+    if cli.reverse {
+        config.reverse = true;
+    }
+    if cli.no_summary {
+        config.summary = false;
+    }
+    if cli.absolute_paths {
+        config.absolute_paths = true;
+    }
+    if cli.dirty_only {
+        config.dirty_only = true;
+    }
+    if let Some(found_min_status) = cli.min_status {
+        config.min_status = Some(found_min_status);
+    }
+    if cli.off_branch {
+        config.off_branch = true;
+    }
+    if cli.local_only {
+        config.local_only = true;
+    }
+    if cli.only_with_changes_to_push {
+        config.only_with_changes_to_push = true;
+    }
+    if let Some(found_email_scope) = cli.email_scope {
+        config.email_scope = found_email_scope;
+    }
+    if let Some(found_email_allowlist) = &cli.email_allowlist {
+        config.email_allowlist = Some(found_email_allowlist.clone());
+    }
+    if cli.skip_bare {
+        config.skip_bare = true;
+    }
+    if cli.follow_symlinks {
+        config.follow_symlinks = true;
+    }
+    if cli.include_hidden {
+        config.include_hidden = true;
+    }
+    if let Some(found_include_size) = cli.include_size {
+        config.include_size = Some(found_include_size);
+    }
+    if cli.include_bundles {
+        config.include_bundles = true;
+    }
+    if cli.flatten_submodules {
+        config.flatten_submodules = true;
     }
-    if let Some(found_paths) = &cli.paths {
+    config.exclude.extend(cli.exclude.iter().cloned());
+    if let Some(found_depth) = cli.depth {
+        config.depth = Some(found_depth);
+    }
+    if let Some(found_fetch_timeout_secs) = cli.fetch_timeout {
+        config.fetch_timeout_secs = Some(found_fetch_timeout_secs);
+    }
+    if let Some(found_fetch_retries) = cli.fetch_retries {
+        config.fetch_retries = found_fetch_retries;
+    }
+    if let Some(found_fetch_concurrency) = cli.fetch_concurrency {
+        config.fetch_concurrency = Some(usize::try_from(found_fetch_concurrency)?);
+    }
+    if let Some(found_fetch_cache_ttl_secs) = cli.fetch_cache_ttl {
+        config.fetch_cache_ttl_secs = Some(found_fetch_cache_ttl_secs);
+    }
+    if cli.status_cache {
+        config.status_cache = true;
+    }
+    if let Some(found_max_width) = cli.max_width {
+        config.max_width = Some(found_max_width);
+    }
+    if let Some(found_format) = &cli.format {
+        config.format = Some(found_format.clone());
+    }
+    if let Some(found_group) = &cli.group {
+        config.select_group(found_group)?;
+    }
+    // Paths from "stdin" are used in addition to the positional paths.
+    let mut found_paths = cli.paths.clone().unwrap_or_default();
+    if cli.stdin {
+        found_paths.extend(read_paths_from_stdin()?);
+    }
+    if cli.paths.is_some() || cli.stdin {
         let current_dir = env::current_dir()?;
-        config.paths = found_paths
-            .iter()
-            .map(|p| current_dir.join(p).canonicalize())
-            .collect::<Result<Vec<PathBuf>, _>>()?;
+        config.paths = Vec::with_capacity(found_paths.len());
+        for path in &found_paths {
+            // Patterns (e.g. "~/src/*/repo") are expanded to the directories that match them,
+            // including a leading "~" since quoted patterns are not expanded by the shell.
+            if let Some(pattern) = path.to_str().filter(|p| Glob::is_pattern(p)) {
+                let pattern = current_dir.join(config::expand_path(Path::new(pattern))?);
+                let matches = Glob::expand(&pattern)?;
+                if matches.is_empty() {
+                    warn!("no directories match pattern: {}", pattern.display());
+                }
+                for found in matches {
+                    config.paths.push(config::resolve_path(&found)?);
+                }
+                continue;
+            }
+            config
+                .paths
+                .push(config::resolve_path(&current_dir.join(path))?);
+        }
     }
     debug!("finalized config options");
 
-    if cli.dry_run {
+    if let Some(DryRun::Config) = cli.dry_run {
         config.print()?;
     } else {
-        let (include_email, include_submodules) = match config.display_mode {
-            DisplayMode::Classic => (false, false),
-            DisplayMode::Json => (true, true),
-            DisplayMode::Standard | DisplayMode::StandardAlphabetical => (true, false),
+        // Errors for individual repositories are reported alongside the results in the JSON display
+        // mode rather than failing the entire run.
+        let error_log = (matches!(config.display_mode, DisplayMode::Json)
+            && config.format.is_none())
+        .then(ErrorLog::default);
+        // The display harness is created first so that the format is validated before collecting.
+        let display_harness = DisplayHarness::new(&config, cli.output.as_deref())?
+            .summary_only(cli.no_display)
+            .null_terminated(cli.null)
+            .error_log(error_log.clone());
+        // A missing SSH key is reported once before collecting rather than for every repository.
+        if cli.remote {
+            credentials::ssh_key_override()?;
+        }
+        let (include_email, include_submodules, include_details) = match config.display_mode {
+            _ if config.format.is_some() => (true, false, false),
+            DisplayMode::Classic | DisplayMode::Compact => (false, false, false),
+            DisplayMode::Json | DisplayMode::Ndjson | DisplayMode::Verbose => (true, true, true),
+            DisplayMode::Standard
+            | DisplayMode::StandardAlphabetical
+            | DisplayMode::StandardGrouped => (true, true, false),
+            DisplayMode::Porcelain => (true, false, false),
+        };
+        // Progress is only displayed to a terminal and never when quiet or in modes intended for
+        // scripting.
+        let show_progress = io::stderr().is_terminal()
+            && cli.verbose.log_level_filter() >= log::LevelFilter::Info
+            && config.format.is_none()
+            && !matches!(
+                config.display_mode,
+                DisplayMode::Json | DisplayMode::Ndjson | DisplayMode::Porcelain
+            );
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let collect_options = CollectOptions {
+            max_depth: config.depth,
+            path_depths: config.path_depths.clone(),
+            exclude: config
+                .exclude
+                .iter()
+                .map(|pattern| Glob::new(pattern))
+                .collect::<Result<Vec<Glob>>>()?,
+            include_email,
+            email_scope: config.email_scope,
+            email_allowlist: config
+                .email_allowlist
+                .as_deref()
+                .map(EmailAllowlist::new)
+                .transpose()?,
+            include_submodules,
+            include_file_counts: include_details,
+            first_parent: cli.first_parent,
+            include_describe: include_details,
+            allow_git_shell: cli.allow_git_shell,
+            skip_bare: config.skip_bare,
+            follow_symlinks: config.follow_symlinks,
+            include_hidden: config.include_hidden,
+            include_bundles: config.include_bundles,
+            flatten_submodules: config.flatten_submodules,
+            include_size: config.include_size,
+            fetch_remote: cli.remote,
+            skip_fetch_if_unclean: cli.no_fetch_on_dirty,
+            find_stale_branches: cli.prune_dry_run,
+            fetch_transport: match (cli.remote_only_https, cli.remote_only_ssh) {
+                (true, _) => Some(Transport::Https),
+                (_, true) => Some(Transport::Ssh),
+                _ => None,
+            },
+            fetch_all_remotes: cli.fetch_all_remotes,
+            origin_head_log: cli.refresh_origin_head.then(OriginHeadLog::default),
+            remote_preference: config.remote_preference.clone(),
+            fetch_only: cli
+                .fetch_only
+                .iter()
+                .map(|pattern| Glob::new(pattern))
+                .collect::<Result<Vec<Glob>>>()?,
+            fetch_password: String::new(),
+            // Prompting is only possible if a user can answer on the terminal.
+            passphrase_prompt: (cli.remote && io::stdin().is_terminal())
+                .then(PassphrasePrompt::default),
+            fetch_timeout: config.fetch_timeout_secs.map(Duration::from_secs),
+            fetch_retries: config.fetch_retries,
+            fetch_limiter: config.fetch_concurrency.map(FetchLimiter::new),
+            fetch_pool: match cli.remote {
+                true => Some(Arc::new(
+                    rayon::ThreadPoolBuilder::new()
+                        .num_threads(match cli.threads_fetch {
+                            Some(threads_fetch) => usize::try_from(threads_fetch)?,
+                            None => DEFAULT_FETCH_THREADS,
+                        })
+                        .thread_name(|index| format!("fetch-{index}"))
+                        .build()?,
+                )),
+                false => None,
+            },
+            fetch_cache: config
+                .fetch_cache_ttl_secs
+                .map(|ttl| FetchCache::load(Duration::from_secs(ttl))),
+            status_cache: config.status_cache.then(StatusCache::load),
+            profile: cli.profile.then(Profile::default),
+            error_log,
+            skipped_directories: Some(SkippedDirectories::default()),
+            show_progress,
+            dirty_only: config.dirty_only,
+            min_status: config.min_status,
+            off_branch: config.off_branch.then(|| config.primary_branches.clone()),
+            local_only: config.local_only,
+            only_with_changes_to_push: config.only_with_changes_to_push,
+            committed_after: cli.since.map(|since| commit_time_cutoff(now, since)),
+            committed_before: cli.stale.map(|stale| commit_time_cutoff(now, stale)),
+        };
+        // The config is resolved once and reused by every iteration when watching.
+        let iteration = || -> Result<bool> {
+            if let Some(found_group) = &cli.group {
+                display_harness.group_header(found_group)?;
+            }
+            let needs_attention = collect_and_display(&config, &collect_options, &display_harness);
+            finish_collection(&collect_options)?;
+            needs_attention
         };
-        for path in &config.paths {
-            debug!("processing path: {} fetch remote: {}", path.display(), cli.remote);
-
-            let repository_collection = RepositoryCollector::run(
-                path,
-                include_email,
-                include_submodules,
-                cli.remote,
-                String::new(),
+        match (cli.dry_run, cli.watch) {
+            (Some(DryRun::Paths), _) => print_targets(&config, &collect_options, cli.null)?,
+            (_, Some(interval)) => {
+                watch::run(Duration::from_secs(interval), || iteration().map(|_| ()))?
+            }
+            (_, None) if cli.copy_urls => {
+                let views = gfld::collect(&config.paths, &collect_options)?;
+                finish_collection(&collect_options)?;
+                print_urls(&views, cli.url_scheme, cli.null)?;
+            }
+            (_, None) if cli.tui && tui::is_supported() => {
+                let views = gfld::collect(&config.paths, &collect_options)?;
+                finish_collection(&collect_options)?;
+                needs_attention = views.iter().any(|view| view.status != Status::Clean);
+                tui::run(
+                    views,
+                    StatusPalette::new(&config.colors)?,
+                    !matches!(config.color_mode, ColorMode::Never),
+                )?;
+            }
+            (_, None) => needs_attention = iteration()?,
+        }
+    }
+    Ok(needs_attention)
+}
+
+/// Writes the fetch and status caches back to disk and prints the number of directories skipped
+/// due to permissions, the repositories whose "origin/HEAD" was refreshed and the slowest
+/// repositories to `stderr` (if enabled) after collecting.
+fn finish_collection(collect_options: &CollectOptions) -> Result<()> {
+    if let Some(fetch_cache) = &collect_options.fetch_cache {
+        fetch_cache.save();
+    }
+    if let Some(status_cache) = &collect_options.status_cache {
+        status_cache.save();
+    }
+    if let Some(skipped_directories) = &collect_options.skipped_directories {
+        match skipped_directories.take().len() {
+            0 => {}
+            1 => writeln!(io::stderr(), "1 directory skipped due to permissions")?,
+            count => writeln!(
+                io::stderr(),
+                "{count} directories skipped due to permissions"
+            )?,
+        }
+    }
+    if let Some(origin_head_log) = &collect_options.origin_head_log {
+        let changes = origin_head_log.take();
+        if !changes.is_empty() {
+            let mut stderr = io::stderr().lock();
+            writeln!(stderr, "refreshed origin/HEAD:")?;
+            for change in changes {
+                writeln!(
+                    stderr,
+                    "  {} -> {} {}",
+                    change.previous.as_deref().unwrap_or("(unset)"),
+                    change.current,
+                    change.path.display()
+                )?;
+            }
+        }
+    }
+    if let Some(profile) = &collect_options.profile {
+        let mut stderr = io::stderr().lock();
+        writeln!(stderr, "slowest repositories:")?;
+        for timing in profile.take_slowest(PROFILE_SLOWEST_COUNT) {
+            writeln!(
+                stderr,
+                "  {:.3}s (local: {:.3}s, fetch: {:.3}s) {}",
+                timing.total.as_secs_f64(),
+                timing.local().as_secs_f64(),
+                timing.fetch.as_secs_f64(),
+                timing.path.display()
             )?;
-            let display_harness = DisplayHarness::new(config.display_mode, config.color_mode);
-            display_harness.run(&repository_collection)?;
         }
     }
     Ok(())
 }
 
+/// Reads newline-delimited paths from `stdin`. Surrounding whitespace is trimmed and blank lines
+/// are ignored. Paths to ".git" directories are replaced by their parents (i.e. the working trees),
+/// which allows piping from tools like `fd`.
+fn read_paths_from_stdin() -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let path = Path::new(line);
+        paths.push(match path.file_name() {
+            Some(file_name) if file_name == ".git" => match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            },
+            _ => path.to_path_buf(),
+        });
+    }
+    Ok(paths)
+}
+
+/// Subtracts the duration from the current time (both in seconds since the Unix epoch) in order to
+/// get a cutoff for the last commit time.
+fn commit_time_cutoff(now: u64, duration: Duration) -> i64 {
+    i64::try_from(now.saturating_sub(duration.as_secs())).unwrap_or(i64::MAX)
+}
+
+/// Prints the paths to the repositories that would be collected from every path in the [`Config`]
+/// (one per line, or terminated by NUL bytes if requested) without opening them. Like when
+/// collecting, repositories found more than once are only printed the first time.
+fn print_targets(config: &Config, collect_options: &CollectOptions, null: bool) -> Result<()> {
+    let terminator = match null {
+        true => '\0',
+        false => '\n',
+    };
+    let mut stdout = io::stdout().lock();
+    let seen = SeenRepositories::default();
+    for path in &config.paths {
+        for target in RepositoryCollector::targets(path, collect_options, &seen)? {
+            write!(stdout, "{}{terminator}", target.display())?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints the remote URLs of the views (converted to the scheme, if provided) sorted and without
+/// duplicates, one per line (or terminated by NUL bytes if requested). Views without a remote are
+/// skipped.
+fn print_urls(views: &[RepositoryView], scheme: Option<UrlScheme>, null: bool) -> Result<()> {
+    let terminator = match null {
+        true => '\0',
+        false => '\n',
+    };
+    let mut found_urls = views
+        .iter()
+        .filter_map(|view| view.url.as_deref())
+        .map(|url| match scheme {
+            Some(scheme) => urls::convert(url, scheme),
+            None => url.to_string(),
+        })
+        .collect::<Vec<String>>();
+    found_urls.sort();
+    found_urls.dedup();
+    let mut stdout = io::stdout().lock();
+    for url in found_urls {
+        write!(stdout, "{url}{terminator}")?;
+    }
+    Ok(())
+}
+
+/// Collects results from every path in the [`Config`] and displays them. Repositories found
+/// through more than one path (or more than once within a path) are only displayed the first
+/// time. In the JSON display mode, the results for all paths are displayed as a single document.
+/// Returns whether or not any collected repository needs attention.
+fn collect_and_display(
+    config: &Config,
+    collect_options: &CollectOptions,
+    display_harness: &DisplayHarness,
+) -> Result<bool> {
+    let mut needs_attention = false;
+    let mut statuses = Vec::new();
+    let seen = SeenRepositories::default();
+    let single_document = matches!(
+        (config.display_mode, &config.format),
+        (DisplayMode::Json, None)
+    );
+    let mut combined_collection = RepositoryCollection::new();
+    for path in &config.paths {
+        debug!(
+            "processing path: {} fetch remote: {}",
+            path.display(),
+            collect_options.fetch_remote
+        );
+
+        if let (DisplayMode::Ndjson, None) = (config.display_mode, &config.format) {
+            let streamed_needs_attention = AtomicBool::new(false);
+            let streamed_statuses = Mutex::new(Vec::new());
+            RepositoryCollector::stream(path, collect_options, &seen, |view| {
+                if view.status != Status::Clean {
+                    streamed_needs_attention.store(true, Ordering::Relaxed);
+                }
+                if let Ok(mut streamed_statuses) = streamed_statuses.lock() {
+                    streamed_statuses.push(view.status);
+                }
+                display_harness.ndjson(&view)
+            })?;
+            needs_attention |= streamed_needs_attention.into_inner();
+            statuses.extend(streamed_statuses.into_inner().unwrap_or_default());
+            continue;
+        }
+
+        let (repository_collection, bundles) =
+            RepositoryCollector::run(path, collect_options, &seen)?;
+        needs_attention |= repository_collection
+            .values()
+            .flatten()
+            .any(|view| view.status != Status::Clean);
+        statuses.extend(
+            repository_collection
+                .values()
+                .flatten()
+                .map(|view| view.status),
+        );
+        if single_document {
+            for (group, views) in repository_collection {
+                combined_collection.entry(group).or_default().extend(views);
+            }
+            continue;
+        }
+        display_harness.run(&repository_collection)?;
+        display_harness.bundles(&bundles)?;
+    }
+    if single_document {
+        display_harness.run(&combined_collection)?;
+    }
+    // The summary covers the reports for all paths.
+    display_harness.all_clean(&statuses)?;
+    display_harness.summary(&statuses)?;
+    Ok(needs_attention)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use collector::RepositoryCollection;
-    use git2::ErrorCode;
+    use gfld::collector::RepositoryCollection;
+    use gfld::repository_view::RepositoryView;
+    use gfld::status::Status;
     use git2::Oid;
     use git2::Signature;
+    use git2::{BranchType, ErrorCode};
     use git2::{Repository, RepositoryInitOptions};
     use pretty_assertions::assert_eq;
-    use repository_view::RepositoryView;
-    use status::Status;
     use std::fs::File;
     use std::path::{Path, PathBuf};
     use std::{fs, io};
@@ -169,10 +676,10 @@ mod tests {
 
         // Repo Four
         let repository = Repository::init_opts(&repo_four, &opts)?;
-        if let Err(e) = repository.remote("origin", "https://github.com/nickgerace/gfold") {
-            if e.code() != ErrorCode::Exists {
-                return Err(e.into());
-            }
+        if let Err(e) = repository.remote("origin", "https://github.com/nickgerace/gfold")
+            && e.code() != ErrorCode::Exists
+        {
+            return Err(e.into());
         }
 
         // Repo Five
@@ -181,19 +688,19 @@ mod tests {
 
         // Repo Six
         let repository = Repository::init_opts(&repo_six, &opts)?;
-        if let Err(e) = repository.remote("fork", "https://github.com/nickgerace/gfold") {
-            if e.code() != ErrorCode::Exists {
-                return Err(e.into());
-            }
+        if let Err(e) = repository.remote("fork", "https://github.com/nickgerace/gfold")
+            && e.code() != ErrorCode::Exists
+        {
+            return Err(e.into());
         }
         commit_head_and_create_branch(&repository, "feat")?;
 
         // Repo Seven
         let repository = Repository::init_opts(&repo_seven, &opts)?;
-        if let Err(e) = repository.remote("origin", "https://github.com/nickgerace/gfold") {
-            if e.code() != ErrorCode::Exists {
-                return Err(e.into());
-            }
+        if let Err(e) = repository.remote("origin", "https://github.com/nickgerace/gfold")
+            && e.code() != ErrorCode::Exists
+        {
+            return Err(e.into());
         }
         commit_head_and_create_branch(&repository, "needtopush")?;
         repository.set_head("refs/heads/needtopush")?;
@@ -210,15 +717,18 @@ mod tests {
             .to_str()
             .expect("could not convert PathBuf to &str")
             .to_string();
+        // Only the repositories with commits have a last commit time.
+        let mut view_eight = RepositoryView::finalize(
+            &worktree_path,
+            Some("working-in-a-tree".to_string()),
+            Status::Unpushed,
+            Some("https://github.com/nickgerace/gfold".to_string()),
+            None,
+            Vec::with_capacity(0),
+        )?;
+        view_eight.last_commit_time = Some(head_commit_time(&worktree_path)?);
         let mut expected_views = vec![
-            RepositoryView::finalize(
-                &worktree_path,
-                Some("working-in-a-tree".to_string()),
-                Status::Unpushed,
-                Some("https://github.com/nickgerace/gfold".to_string()),
-                None,
-                Vec::with_capacity(0),
-            )?,
+            view_eight,
             RepositoryView::finalize(
                 &repo_one,
                 Some("HEAD".to_string()),
@@ -252,6 +762,24 @@ mod tests {
             .to_str()
             .expect("could not convert PathBuf to &str")
             .to_string();
+        let mut view_six = RepositoryView::finalize(
+            &repo_six,
+            Some(initial_head.to_string()),
+            Status::Unpushed,
+            Some("https://github.com/nickgerace/gfold".to_string()),
+            None,
+            Vec::with_capacity(0),
+        )?;
+        view_six.last_commit_time = Some(head_commit_time(&repo_six)?);
+        let mut view_seven = RepositoryView::finalize(
+            &repo_seven,
+            Some("needtopush".to_string()),
+            Status::Unpushed,
+            Some("https://github.com/nickgerace/gfold".to_string()),
+            None,
+            Vec::with_capacity(0),
+        )?;
+        view_seven.last_commit_time = Some(head_commit_time(&repo_seven)?);
         let mut nested_expected_views_raw = vec![
             RepositoryView::finalize(
                 &repo_four,
@@ -269,28 +797,18 @@ mod tests {
                 None,
                 Vec::with_capacity(0),
             )?,
-            RepositoryView::finalize(
-                &repo_six,
-                Some(initial_head.to_string()),
-                Status::Unpushed,
-                Some("https://github.com/nickgerace/gfold".to_string()),
-                None,
-                Vec::with_capacity(0),
-            )?,
-            RepositoryView::finalize(
-                &repo_seven,
-                Some("needtopush".to_string()),
-                Status::Unpushed,
-                Some("https://github.com/nickgerace/gfold".to_string()),
-                None,
-                Vec::with_capacity(0),
-            )?,
+            view_six,
+            view_seven,
         ];
         nested_expected_views_raw.sort_by(|a, b| a.name.cmp(&b.name));
         expected_collection.insert(Some(nested_expected_views_key), nested_expected_views_raw);
 
         // Generate a collection.
-        let found_collection = RepositoryCollector::run(root.path(), false, false, false, String::from(""))?;
+        let (found_collection, _) = RepositoryCollector::run(
+            root.path(),
+            &CollectOptions::default(),
+            &SeenRepositories::default(),
+        )?;
 
         // Ensure the found collection matches our expected one. Sort the collection for the
         // assertion.
@@ -303,17 +821,114 @@ mod tests {
             expected_collection,     // expected
             found_collection_sorted  // actual
         );
+
+        // Repositories whose branches need more than a push are collected separately, since their
+        // statuses are what we are after here rather than their complete views:
+        // └── root
+        //     └── attention
+        //         ├── conflicted (repo with an unfinished merge that both added "file")
+        //         └── diverged (repo with one commit to push and one commit to pull)
+        let attention = create_directory(&root, "attention")?;
+        let repo_conflicted = create_directory(&attention, "conflicted")?;
+        let repo_diverged = create_directory(&attention, "diverged")?;
+
+        // Repo Diverged. The remote-tracking branch and the local branch both have a commit on top
+        // of the shared commit. The local commit adds a file, so that the two commits differ.
+        let repository = Repository::init_opts(&repo_diverged, &opts)?;
+        repository.remote("origin", "https://github.com/nickgerace/gfold")?;
+        commit(&repository, "HEAD")?;
+        commit(&repository, "refs/remotes/origin/main")?;
+        create_file(&repo_diverged)?;
+        let mut index = repository.index()?;
+        index.add_path(Path::new("file"))?;
+        index.write()?;
+        commit(&repository, "HEAD")?;
+        repository
+            .find_branch(initial_head, BranchType::Local)?
+            .set_upstream(Some("origin/main"))?;
+
+        // Repo Conflicted. Both branches add "file" with different contents on top of the shared
+        // commit, so merging the other branch leaves an add/add conflict behind.
+        let repository = Repository::init_opts(&repo_conflicted, &opts)?;
+        let shared = repository.find_commit(commit(&repository, "HEAD")?)?;
+        repository.branch("theirs", &shared, false)?;
+        let file = repo_conflicted.join("file");
+        let mut index = repository.index()?;
+        fs::write(&file, "theirs")?;
+        index.add_path(Path::new("file"))?;
+        index.write()?;
+        let tree = repository.find_tree(index.write_tree()?)?;
+        let signature = Signature::now("Bob", "bob@bob")?;
+        repository.commit(
+            Some("refs/heads/theirs"),
+            &signature,
+            &signature,
+            "theirs",
+            &tree,
+            &[&shared],
+        )?;
+        fs::write(&file, "ours")?;
+        index.add_path(Path::new("file"))?;
+        index.write()?;
+        commit(&repository, "HEAD")?;
+        let theirs = repository.find_branch("theirs", BranchType::Local)?;
+        let theirs = repository.reference_to_annotated_commit(theirs.get())?;
+        repository.merge(&[&theirs], None, None)?;
+
+        let (found_collection, _) = RepositoryCollector::run(
+            &attention,
+            &CollectOptions::default(),
+            &SeenRepositories::default(),
+        )?;
+        let found_views: Vec<RepositoryView> = found_collection.into_values().flatten().collect();
+        let found = |name: &str| found_views.iter().find(|view| view.name == name);
+        let diverged = found("diverged").expect("diverged repository was not collected");
+        assert_eq!(Status::Diverged, diverged.status);
+        assert_eq!((1, 1), (diverged.ahead, diverged.behind));
+        let conflicted = found("conflicted").expect("conflicted repository was not collected");
+        assert_eq!(Status::Conflicted, conflicted.status);
+        assert_eq!(Some("merge"), conflicted.operation.as_deref());
+
+        // The severity order drives filtering, sorting and the exit code.
+        let mut statuses = vec![
+            conflicted.status,
+            diverged.status,
+            Status::Unpushed,
+            Status::Clean,
+            Status::Unpulled,
+            Status::Unclean,
+        ];
+        statuses.sort();
+        assert_eq!(
+            vec![
+                Status::Clean,
+                Status::Unclean,
+                Status::Unpushed,
+                Status::Unpulled,
+                Status::Diverged,
+                Status::Conflicted,
+            ],
+            statuses
+        );
         Ok(())
     }
 
+    fn head_commit_time(path: &Path) -> Result<i64, git2::Error> {
+        Ok(Repository::open(path)?
+            .head()?
+            .peel_to_commit()?
+            .time()
+            .seconds())
+    }
+
     fn create_directory<P: AsRef<Path>>(parent: P, name: &str) -> io::Result<PathBuf> {
         let parent = parent.as_ref();
         let new_directory = parent.join(name);
 
-        if let Err(e) = fs::create_dir(&new_directory) {
-            if e.kind() != io::ErrorKind::AlreadyExists {
-                return Err(e);
-            }
+        if let Err(e) = fs::create_dir(&new_directory)
+            && e.kind() != io::ErrorKind::AlreadyExists
+        {
+            return Err(e);
         }
         Ok(new_directory)
     }
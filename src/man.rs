@@ -0,0 +1,171 @@
+//! This module contains the functionality for generating a man page from the CLI definition.
+
+use std::io::{self, Write};
+
+use clap::Command;
+
+/// Write a man page (in roff format) for the command to the writer. The page contains the help
+/// text for the command, its options and its subcommands.
+pub(crate) fn generate(command: &Command, writer: &mut impl Write) -> io::Result<()> {
+    let name = command.get_name();
+    let version = command.get_version().unwrap_or_default();
+    writeln!(
+        writer,
+        ".TH {} 1 \"\" \"{name} {version}\"",
+        escape(&name.to_uppercase())
+    )?;
+
+    writeln!(writer, ".SH NAME")?;
+    writeln!(
+        writer,
+        "{} \\- {}",
+        escape(name),
+        escape(env!("CARGO_PKG_DESCRIPTION"))
+    )?;
+
+    writeln!(writer, ".SH SYNOPSIS")?;
+    writeln!(
+        writer,
+        "\\fB{}\\fR [\\fIOPTIONS\\fR] [\\fIPATHS\\fR]... [\\fICOMMAND\\fR]",
+        escape(name)
+    )?;
+
+    if let Some(about) = command.get_about() {
+        writeln!(writer, ".SH DESCRIPTION")?;
+        paragraphs(&about.to_string(), writer)?;
+    }
+
+    writeln!(writer, ".SH OPTIONS")?;
+    for arg in command.get_arguments().filter(|arg| !arg.is_hide_set()) {
+        let value_names = arg
+            .get_value_names()
+            .map(|value_names| {
+                value_names
+                    .iter()
+                    .map(|value_name| format!("\\fI<{}>\\fR", escape(value_name)))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            })
+            .unwrap_or_default();
+        let mut names = Vec::new();
+        if let Some(short) = arg.get_short() {
+            names.push(format!("\\fB\\-{}\\fR", escape(&short.to_string())));
+        }
+        if let Some(long) = arg.get_long() {
+            names.push(format!("\\fB\\-\\-{}\\fR", escape(long)));
+        }
+        writeln!(writer, ".TP")?;
+        match (names.is_empty(), arg.get_action().takes_values()) {
+            (true, _) => writeln!(writer, "[{value_names}]...")?,
+            (false, true) => writeln!(writer, "{} {value_names}", names.join(", "))?,
+            (false, false) => writeln!(writer, "{}", names.join(", "))?,
+        }
+        if let Some(help) = arg.get_long_help().or(arg.get_help()) {
+            writeln!(writer, "{}", escape(&help.to_string()))?;
+        }
+        // Flags (e.g. "--stdin") report "true" and "false" as possible values, which are omitted.
+        let possible_values = arg
+            .get_possible_values()
+            .iter()
+            .filter(|value| !value.is_hide_set())
+            .map(|value| value.get_name().to_string())
+            .collect::<Vec<String>>();
+        if arg.get_action().takes_values() && !possible_values.is_empty() {
+            writeln!(writer, ".br")?;
+            writeln!(
+                writer,
+                "[possible values: {}]",
+                escape(&possible_values.join(", "))
+            )?;
+        }
+    }
+
+    let subcommands: Vec<&Command> = command
+        .get_subcommands()
+        .filter(|subcommand| !subcommand.is_hide_set())
+        .collect();
+    if !subcommands.is_empty() {
+        writeln!(writer, ".SH COMMANDS")?;
+        for subcommand in subcommands {
+            // Only positional arguments are listed for subcommands.
+            let mut usage = format!("\\fB{} {}\\fR", escape(name), escape(subcommand.get_name()));
+            for arg in subcommand.get_positionals() {
+                let value_name = match arg.get_value_names() {
+                    Some([value_name, ..]) => value_name.as_str(),
+                    _ => arg.get_id().as_str(),
+                };
+                usage.push_str(&format!(" \\fI<{}>\\fR", escape(value_name)));
+            }
+            writeln!(writer, ".TP")?;
+            writeln!(writer, "{usage}")?;
+            if let Some(about) = subcommand.get_about() {
+                writeln!(writer, "{}", escape(&about.to_string()))?;
+            }
+        }
+    }
+
+    writeln!(writer, ".SH VERSION")?;
+    writeln!(writer, "v{}", escape(version))
+}
+
+/// Write text as paragraphs. Paragraphs are separated by blank lines and indented lines are
+/// written as-is (e.g. for lists of paths).
+fn paragraphs(text: &str, writer: &mut impl Write) -> io::Result<()> {
+    let mut preformatted = false;
+    for line in text.lines() {
+        let indented = line.starts_with(char::is_whitespace) && !line.trim().is_empty();
+        if indented != preformatted {
+            match indented {
+                true => writeln!(writer, ".RS 4\n.nf")?,
+                false => writeln!(writer, ".fi\n.RE")?,
+            }
+            preformatted = indented;
+        }
+        match line.trim().is_empty() {
+            true if !preformatted => writeln!(writer, ".PP")?,
+            true => {}
+            false => writeln!(writer, "{}", escape(line.trim()))?,
+        }
+    }
+    if preformatted {
+        writeln!(writer, ".fi\n.RE")?;
+    }
+    Ok(())
+}
+
+/// Escape text for roff. Backslashes and dashes are escaped everywhere and lines that would start
+/// with a control character are prefixed with a zero-width character.
+fn escape(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let line = line.replace('\\', "\\e").replace('-', "\\-");
+            match line.starts_with(['.', '\'']) {
+                true => format!("\\&{line}"),
+                false => line,
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    use crate::args::Cli;
+
+    #[allow(clippy::panic_in_result_fn)]
+    #[test]
+    fn man_page() -> anyhow::Result<()> {
+        let mut page = Vec::new();
+        generate(&Cli::command(), &mut page)?;
+        let page = String::from_utf8(page)?;
+        assert!(page.starts_with(".TH GFLD 1"));
+        assert!(page.contains("\\fB\\-c\\fR, \\fB\\-\\-color\\-mode\\fR"));
+        assert!(page.contains("[possible values: always, compatibility, never]"));
+        assert!(page.contains("\\fBgfld completions\\fR"));
+        assert!(page.contains("$XDG_CONFIG_HOME/gfld.toml"));
+        Ok(())
+    }
+}
@@ -0,0 +1,454 @@
+//! This module contains [`Regex`], which is used to match values against user-provided patterns.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use anyhow::{Result, anyhow};
+
+/// A compiled regular expression. Only matching is supported (i.e. there are no captures), so
+/// greedy and lazy quantifiers behave the same. Patterns support the following syntax:
+///
+/// - `.` matches any character
+/// - `^` and `$` match the start and end of the value
+/// - `*`, `+`, `?`, `{n}`, `{n,}` and `{n,m}` repeat the preceding item
+/// - `a|b` matches either alternative and `(...)` groups items
+/// - `[abc]`, `[a-z]` and `[^abc]` match (or do not match) a single character from the class
+/// - `\d`, `\w` and `\s` (and their negations `\D`, `\W` and `\S`) match character types and any
+///   other escaped character matches itself (e.g. `\.`)
+/// - a leading `(?i)` makes the pattern case-insensitive
+///
+/// Like `grep`, a pattern matches if it matches anywhere in the value unless anchored.
+#[derive(Debug, Clone)]
+pub(crate) struct Regex {
+    pattern: String,
+    program: Vec<Inst>,
+    case_insensitive: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Alternate(Vec<Node>),
+    AnyChar,
+    Class(Class),
+    Concat(Vec<Node>),
+    End,
+    Literal(char),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+    Start,
+}
+
+/// An instruction of the compiled program, which is run by a Pike VM (see [`Regex::is_match()`]).
+/// Jumps and splits refer to instructions by index.
+#[derive(Debug, Clone)]
+enum Inst {
+    AnyChar,
+    Class(Class),
+    End,
+    Jump(usize),
+    Literal(char),
+    Match,
+    Split(usize, usize),
+    Start,
+}
+
+#[derive(Debug, Clone)]
+struct Class {
+    negated: bool,
+    items: Vec<ClassItem>,
+}
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Digit(bool),
+    Range(char, char),
+    Space(bool),
+    Word(bool),
+}
+
+impl Regex {
+    /// Compile a regular expression. An error is returned if the pattern is malformed (e.g. it
+    /// contains an unclosed group or a quantifier without an item to repeat).
+    pub(crate) fn new(pattern: &str) -> Result<Self> {
+        let (case_insensitive, rest) = match pattern.strip_prefix("(?i)") {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+        let mut parser = Parser {
+            chars: rest.chars().peekable(),
+        };
+        let node = parser.alternate().map_err(|e| anyhow!("{e}: {pattern}"))?;
+        if let Some(c) = parser.chars.next() {
+            return Err(anyhow!("unexpected '{c}' in regex: {pattern}"));
+        }
+        let mut program = Vec::new();
+        Self::compile(node, &mut program);
+        program.push(Inst::Match);
+        Ok(Self {
+            pattern: pattern.to_string(),
+            program,
+            case_insensitive,
+        })
+    }
+
+    /// Return the original pattern.
+    pub(crate) fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Check if the pattern matches anywhere in the value. All possible matches are tracked at
+    /// once (one thread per instruction) while stepping through the value, so matching takes time
+    /// linear in the length of the value regardless of the pattern (i.e. there is no
+    /// backtracking).
+    pub(crate) fn is_match(&self, value: &str) -> bool {
+        let value: Vec<char> = match self.case_insensitive {
+            true => value.to_lowercase().chars().collect(),
+            false => value.chars().collect(),
+        };
+        // The position (plus one) that each instruction was last added at, so that each
+        // instruction is only added once per position.
+        let mut added = vec![0; self.program.len()];
+        let mut threads = Vec::new();
+        for pos in 0..=value.len() {
+            // A new thread starts at every position, since the pattern can match anywhere.
+            if self.add_thread(&mut threads, &mut added, 0, pos, value.len()) {
+                return true;
+            }
+            let Some(c) = value.get(pos) else {
+                break;
+            };
+            let mut next = Vec::with_capacity(threads.len());
+            for pc in threads {
+                let consumed = match &self.program[pc] {
+                    Inst::AnyChar => true,
+                    Inst::Class(class) => self.class_matches(class, *c),
+                    Inst::Literal(literal) => *c == self.fold(*literal),
+                    _ => false,
+                };
+                if consumed && self.add_thread(&mut next, &mut added, pc + 1, pos + 1, value.len())
+                {
+                    return true;
+                }
+            }
+            threads = next;
+        }
+        false
+    }
+
+    /// Add a thread for the instruction at the position, following jumps, splits and anchors until
+    /// instructions that consume a character are reached. Returns `true` if a match is reached.
+    fn add_thread(
+        &self,
+        threads: &mut Vec<usize>,
+        added: &mut [usize],
+        pc: usize,
+        pos: usize,
+        len: usize,
+    ) -> bool {
+        let mut stack = vec![pc];
+        while let Some(pc) = stack.pop() {
+            if added[pc] == pos + 1 {
+                continue;
+            }
+            added[pc] = pos + 1;
+            match self.program[pc] {
+                Inst::End if pos == len => stack.push(pc + 1),
+                Inst::Start if pos == 0 => stack.push(pc + 1),
+                Inst::End | Inst::Start => {}
+                Inst::Jump(target) => stack.push(target),
+                Inst::Match => return true,
+                Inst::Split(first, second) => stack.extend([second, first]),
+                Inst::AnyChar | Inst::Class(_) | Inst::Literal(_) => threads.push(pc),
+            }
+        }
+        false
+    }
+
+    /// Append the instructions for the node to the program.
+    fn compile(node: Node, program: &mut Vec<Inst>) {
+        match node {
+            Node::Alternate(alternatives) => {
+                let count = alternatives.len();
+                let mut jumps = Vec::with_capacity(count);
+                for (index, alternative) in alternatives.into_iter().enumerate() {
+                    if index + 1 == count {
+                        Self::compile(alternative, program);
+                        break;
+                    }
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    Self::compile(alternative, program);
+                    jumps.push(program.len());
+                    program.push(Inst::Jump(0));
+                    program[split] = Inst::Split(split + 1, program.len());
+                }
+                let end = program.len();
+                for jump in jumps {
+                    program[jump] = Inst::Jump(end);
+                }
+            }
+            Node::AnyChar => program.push(Inst::AnyChar),
+            Node::Class(class) => program.push(Inst::Class(class)),
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    Self::compile(node, program);
+                }
+            }
+            Node::End => program.push(Inst::End),
+            Node::Literal(literal) => program.push(Inst::Literal(literal)),
+            Node::Repeat { node, min, max } => {
+                for _ in 0..min {
+                    Self::compile((*node).clone(), program);
+                }
+                match max {
+                    Some(max) => {
+                        // Each optional repetition skips to the end, since the remaining ones
+                        // cannot match either.
+                        let mut splits = Vec::with_capacity(max - min);
+                        for _ in min..max {
+                            splits.push(program.len());
+                            program.push(Inst::Split(program.len() + 1, 0));
+                            Self::compile((*node).clone(), program);
+                        }
+                        let end = program.len();
+                        for split in splits {
+                            program[split] = Inst::Split(split + 1, end);
+                        }
+                    }
+                    None => {
+                        let split = program.len();
+                        program.push(Inst::Split(split + 1, 0));
+                        Self::compile(*node, program);
+                        program.push(Inst::Jump(split));
+                        program[split] = Inst::Split(split + 1, program.len());
+                    }
+                }
+            }
+            Node::Start => program.push(Inst::Start),
+        }
+    }
+
+    fn class_matches(&self, class: &Class, c: char) -> bool {
+        let found = class.items.iter().any(|item| match item {
+            ClassItem::Digit(negated) => c.is_ascii_digit() != *negated,
+            ClassItem::Range(start, end) => (self.fold(*start)..=self.fold(*end)).contains(&c),
+            ClassItem::Space(negated) => c.is_whitespace() != *negated,
+            ClassItem::Word(negated) => (c.is_alphanumeric() || c == '_') != *negated,
+        });
+        found != class.negated
+    }
+
+    /// Lowercase pattern characters if the pattern is case-insensitive (values are lowercased
+    /// before matching).
+    fn fold(&self, c: char) -> char {
+        match self.case_insensitive {
+            true => c.to_lowercase().next().unwrap_or(c),
+            false => c,
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn alternate(&mut self) -> Result<Node> {
+        let mut alternatives = vec![self.concat()?];
+        while self.chars.next_if_eq(&'|').is_some() {
+            alternatives.push(self.concat()?);
+        }
+        Ok(match alternatives.len() {
+            1 => alternatives.remove(0),
+            _ => Node::Alternate(alternatives),
+        })
+    }
+
+    fn concat(&mut self) -> Result<Node> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.chars.peek().copied() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            self.chars.next();
+            let node = match c {
+                '(' => {
+                    let node = self.alternate()?;
+                    if self.chars.next() != Some(')') {
+                        return Err(anyhow!("unclosed group in regex"));
+                    }
+                    node
+                }
+                '[' => Node::Class(self.class()?),
+                '.' => Node::AnyChar,
+                '^' => Node::Start,
+                '$' => Node::End,
+                '\\' => match self.escape()? {
+                    Some(item) => Node::Class(Class {
+                        negated: false,
+                        items: vec![item],
+                    }),
+                    None => Node::Literal(self.escaped_literal()?),
+                },
+                '*' | '+' | '?' | '{' => {
+                    return Err(anyhow!("quantifier '{c}' has nothing to repeat in regex"));
+                }
+                c => Node::Literal(c),
+            };
+            nodes.push(self.quantified(node)?);
+        }
+        Ok(match nodes.len() {
+            1 => nodes.remove(0),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn quantified(&mut self, node: Node) -> Result<Node> {
+        let (min, max) = match self.chars.next_if(|c| matches!(c, '*' | '+' | '?' | '{')) {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some(_) => self.repetition()?,
+            None => return Ok(node),
+        };
+        // Lazy quantifiers are accepted, but they match the same values as greedy ones.
+        self.chars.next_if_eq(&'?');
+        Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+        })
+    }
+
+    /// Parse the range of a `{n}`, `{n,}` or `{n,m}` quantifier (after the opening brace).
+    fn repetition(&mut self) -> Result<(usize, Option<usize>)> {
+        let min = self.number()?;
+        let max = match self.chars.next_if_eq(&',') {
+            Some(_) if self.chars.peek() == Some(&'}') => None,
+            Some(_) => Some(self.number()?),
+            None => Some(min),
+        };
+        if self.chars.next() != Some('}') {
+            return Err(anyhow!("unclosed repetition in regex"));
+        }
+        if max.is_some_and(|max| max < min) {
+            return Err(anyhow!("invalid repetition range in regex"));
+        }
+        Ok((min, max))
+    }
+
+    fn number(&mut self) -> Result<usize> {
+        let mut digits = String::new();
+        while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit()) {
+            digits.push(c);
+        }
+        digits
+            .parse()
+            .map_err(|_| anyhow!("expected a number in repetition in regex"))
+    }
+
+    fn class(&mut self) -> Result<Class> {
+        let negated = self.chars.next_if_eq(&'^').is_some();
+        let mut items = Vec::new();
+        loop {
+            let start = match self.chars.next() {
+                Some(']') if !items.is_empty() => break,
+                Some('\\') => match self.escape()? {
+                    Some(item) => {
+                        items.push(item);
+                        continue;
+                    }
+                    None => self.escaped_literal()?,
+                },
+                Some(c) => c,
+                None => return Err(anyhow!("unclosed character class in regex")),
+            };
+            let mut lookahead = self.chars.clone();
+            let end = match (lookahead.next(), lookahead.next()) {
+                (Some('-'), Some(end)) if end != ']' => {
+                    self.chars.next();
+                    self.chars.next();
+                    end
+                }
+                _ => start,
+            };
+            items.push(ClassItem::Range(start, end));
+        }
+        Ok(Class { negated, items })
+    }
+
+    /// Parse an escaped character type (e.g. `\d`), if the next character is one. Otherwise, the
+    /// escaped character is left for [`Self::escaped_literal()`].
+    fn escape(&mut self) -> Result<Option<ClassItem>> {
+        let item = match self.chars.peek() {
+            Some('d') => ClassItem::Digit(false),
+            Some('D') => ClassItem::Digit(true),
+            Some('s') => ClassItem::Space(false),
+            Some('S') => ClassItem::Space(true),
+            Some('w') => ClassItem::Word(false),
+            Some('W') => ClassItem::Word(true),
+            Some(_) => return Ok(None),
+            None => return Err(anyhow!("trailing backslash in regex")),
+        };
+        self.chars.next();
+        Ok(Some(item))
+    }
+
+    fn escaped_literal(&mut self) -> Result<char> {
+        self.chars
+            .next()
+            .ok_or_else(|| anyhow!("trailing backslash in regex"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[allow(clippy::panic_in_result_fn)]
+    #[test]
+    fn regex_matching() -> Result<()> {
+        let work = Regex::new(r"@(work|corp)\.example\.com$")?;
+        assert!(work.is_match("nick@work.example.com"));
+        assert!(work.is_match("nick@corp.example.com"));
+        assert!(!work.is_match("nick@work.example.com.evil"));
+        assert!(!work.is_match("nick@personal.example"));
+
+        let anchored = Regex::new(r"(?i)^[a-z]+\.\w+@[^@]+$")?;
+        assert!(anchored.is_match("Nick.Gerace@Example.com"));
+        assert!(!anchored.is_match("nick@example.com"));
+
+        let repeated = Regex::new(r"^a{2,3}b?\d+$")?;
+        assert!(repeated.is_match("aab1"));
+        assert!(repeated.is_match("aaa42"));
+        assert!(!repeated.is_match("a1"));
+        assert!(!repeated.is_match("aaaa1"));
+
+        let nested = Regex::new(r"^(a*)*$|x{0,2}y")?;
+        assert!(nested.is_match("aaa"));
+        assert!(nested.is_match("y"));
+        assert!(!nested.is_match("aab"));
+
+        assert!(Regex::new("(unclosed").is_err());
+        assert!(Regex::new("*nothing").is_err());
+        Ok(())
+    }
+
+    #[allow(clippy::panic_in_result_fn)]
+    #[test]
+    fn regex_matching_is_linear() -> Result<()> {
+        // These patterns take exponential time with a backtracking matcher.
+        let value = "a".repeat(10_000);
+        let started = Instant::now();
+        assert!(!Regex::new("^(a|a)*b$")?.is_match(&value));
+        assert!(!Regex::new("^(a*)*b$")?.is_match(&value));
+        assert!(Regex::new("^(a|aa)+$")?.is_match(&value));
+        assert!(started.elapsed() < Duration::from_secs(1));
+        Ok(())
+    }
+}
@@ -1,20 +1,51 @@
 //! This module contains [`RepositoryView`], which provides the [`Status`]
 //! and general overview of the state of a given Git repository.
 
-use std::io::BufReader;
-use std::path::Path;
-use std::{fs::File, path::PathBuf};
+use std::collections::HashSet;
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use anyhow::{Result, anyhow};
-use git2::{Cred, ErrorCode, FetchOptions, RemoteCallbacks, Repository};
-use log::{debug, error, trace};
+use credentials::{HttpsCredentials, SshCredentials};
+use error_log::ErrorLog;
+use git_shell::GitShellStatus;
+use git2::{
+    BranchType, ConfigLevel, DescribeOptions, ErrorClass, ErrorCode, FetchOptions, Oid, Reference,
+    Remote, RemoteCallbacks, Repository, RepositoryOpenFlags, RepositoryState, StatusOptions,
+};
+use log::{debug, error, trace, warn};
+use origin_head::OriginHeadLog;
+use passphrase::PassphrasePrompt;
+use profile::Timing;
 use serde::{Deserialize, Serialize};
-use ssh2_config::{ParseRule, SshConfig};
 use submodule_view::SubmoduleView;
 
+use crate::collector::{CollectOptions, Transport};
+use crate::config::{EmailScope, SizeScope};
+use crate::logging::RepositoryScope;
 use crate::status::Status;
 
-mod submodule_view;
+mod cache_file;
+pub mod credentials;
+pub mod error_log;
+pub mod fetch_cache;
+pub mod fetch_limiter;
+mod git_shell;
+pub mod origin_head;
+pub mod passphrase;
+pub mod profile;
+pub mod status_cache;
+pub mod submodule_view;
+
+/// The delay before the first retry of a failed fetch, which doubles for each subsequent retry.
+const FETCH_RETRY_BACKOFF: Duration = Duration::from_millis(500);
 
 /// A collection of results for a Git repository at a given path.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -33,27 +64,182 @@ pub struct RepositoryView {
 
     /// The email used in either the local or global config for the repository.
     pub email: Option<String>,
+    /// Whether or not the effective email matches the email allowlist (a missing email never
+    /// matches). The value will be `None` if there is no allowlist.
+    pub email_allowed: Option<bool>,
     /// Views of submodules found within the repository.
     pub submodules: Vec<SubmoduleView>,
+
+    /// The number of commits on the current branch that are not on its upstream tracking branch.
+    /// The value will be zero if there is no upstream tracking branch.
+    pub ahead: usize,
+    /// The number of commits on the upstream tracking branch that are not on the current branch.
+    /// The value will be zero if there is no upstream tracking branch.
+    pub behind: usize,
+    /// The number of stashed changes.
+    pub stashes: usize,
+    /// The name of the upstream tracking branch of the current branch (e.g. "origin/main"). The
+    /// value will be `None` if there is no upstream tracking branch.
+    pub upstream: Option<String>,
+
+    /// The number of files with unstaged changes in the working tree.
+    pub modified: usize,
+    /// The number of untracked files in the working tree (ignored files are not counted).
+    pub untracked: usize,
+    /// The number of files with changes staged in the index.
+    pub staged: usize,
+
+    /// The time of the HEAD commit in seconds since the Unix epoch. The value will be `None` if
+    /// there is no commit (e.g. the repository is empty).
+    pub last_commit_time: Option<i64>,
+    /// The most recent tag reachable from HEAD in the style of `git describe` (e.g.
+    /// "v1.2.3-4-gabcdef0"). The value will be `None` if no tag is reachable.
+    pub describe: Option<String>,
+    /// Whether or not the repository uses Git LFS (i.e. it has LFS objects or an LFS filter in
+    /// the `.gitattributes` file at the root of the working tree).
+    pub lfs: bool,
+    /// Whether or not the repository is a shallow clone (i.e. its history is truncated).
+    pub shallow: bool,
+    /// The default branch of "origin" (e.g. "main"), as recorded by "refs/remotes/origin/HEAD".
+    /// The value will be `None` if "origin/HEAD" is not set locally.
+    pub remote_default_branch: Option<String>,
+    /// The on-disk size of the repository in bytes (see [`SizeScope`]). The value will be `None`
+    /// if the size was not computed or could not be computed (e.g. due to a permission error).
+    pub size_bytes: Option<u64>,
+    /// The number of remote-tracking branches that no longer exist on the fetched remotes (i.e.
+    /// they would be pruned). The value will be `None` if the remotes were not fetched.
+    pub stale_branches: Option<usize>,
+    /// The operation in progress in the repository (e.g. "rebase-interactive" or "cherry-pick").
+    /// The value will be `None` if no operation is in progress.
+    pub operation: Option<String>,
+    /// Whether or not the repository is a superproject whose submodules are collected as views of
+    /// their own (i.e. submodules are flattened). The value will be `false` otherwise.
+    pub superproject: bool,
+}
+
+/// A [`RepositoryView`] whose local state has been collected, which is the first stage of
+/// collection (see [`RepositoryView::collect_local()`]). If the remote needs to be fetched, the
+/// fetch (along with everything that depends on the remote-tracking branches, e.g. the status and
+/// the ahead and behind counts) is left for the second stage (see [`Self::finish()`]). Stages run
+/// separately, so that the number of fetches running at the same time is independent of the number
+/// of threads collecting local state.
+#[derive(Debug)]
+pub struct LocalView {
+    view: RepositoryView,
+    path: PathBuf,
+    needs_fetch: bool,
+    /// The time spent on the first stage.
+    elapsed: Duration,
+}
+
+impl LocalView {
+    /// Whether or not the remote still needs to be fetched in the second stage. Otherwise,
+    /// finishing the view is instant.
+    pub fn needs_fetch(&self) -> bool {
+        self.needs_fetch
+    }
+
+    /// Run the second stage of collection, which fetches the remote (if needed) and refreshes the
+    /// view with the fetched remote-tracking branches.
+    pub fn finish(mut self, options: &CollectOptions) -> Result<RepositoryView> {
+        let _scope = RepositoryScope::enter(&self.path);
+        let started = Instant::now();
+        let mut fetch = Duration::ZERO;
+        let result = match self.needs_fetch {
+            true => RepositoryView::fetch(&mut self.view, &self.path, options, &mut fetch),
+            false => Ok(()),
+        };
+        RepositoryView::record_timing(&self.path, options, self.elapsed + started.elapsed(), fetch);
+        result.map(|()| self.view)
+    }
 }
 
 impl RepositoryView {
-    /// Generates a collector for a given path.
-    pub fn new(
+    /// Generates a collector for a given path. The view will be `None` if the repository was
+    /// skipped based on the provided options. This runs both stages of collection (see
+    /// [`LocalView`]) on the current thread.
+    pub fn new(repo_path: &Path, options: &CollectOptions) -> Result<Option<RepositoryView>> {
+        Self::collect_local(repo_path, options)?
+            .map(|local| local.finish(options))
+            .transpose()
+    }
+
+    /// Run the first stage of collection for a given path (see [`LocalView`]). The view will be
+    /// `None` if the repository was skipped based on the provided options.
+    pub fn collect_local(repo_path: &Path, options: &CollectOptions) -> Result<Option<LocalView>> {
+        let _scope = RepositoryScope::enter(repo_path);
+        let started = Instant::now();
+        let local = Self::collect(repo_path, options);
+        let elapsed = started.elapsed();
+        match local {
+            Ok(Some((view, needs_fetch))) => Ok(Some(LocalView {
+                view,
+                path: repo_path.to_path_buf(),
+                needs_fetch,
+                elapsed,
+            })),
+            // Skipped repositories (and errors) have no second stage, so they are timed now.
+            local => {
+                Self::record_timing(repo_path, options, elapsed, Duration::ZERO);
+                local.map(|_| None)
+            }
+        }
+    }
+
+    /// Log the time spent collecting the view and record it in the profile (if any).
+    fn record_timing(repo_path: &Path, options: &CollectOptions, total: Duration, fetch: Duration) {
+        let timing = Timing {
+            path: repo_path.to_path_buf(),
+            total,
+            fetch,
+        };
+        debug!(
+            "collected in {:.3}s (local: {:.3}s, fetch: {:.3}s): {}",
+            timing.total.as_secs_f64(),
+            timing.local().as_secs_f64(),
+            timing.fetch.as_secs_f64(),
+            repo_path.display()
+        );
+        if let Some(profile) = &options.profile {
+            profile.record(timing);
+        }
+    }
+
+    /// Collect the local state for [`Self::collect_local()`] and decide whether or not the remote
+    /// needs to be fetched.
+    fn collect(
         repo_path: &Path,
-        include_email: bool,
-        include_submodules: bool,
-        fetch_remote: bool,
-        fetch_password: String,
-    ) -> Result<RepositoryView> {
+        options: &CollectOptions,
+    ) -> Result<Option<(RepositoryView, bool)>> {
         debug!(
             "attempting to generate collector for repository_view at path: {}",
             repo_path.display()
         );
 
-        let repo = match Repository::open(repo_path) {
+        let mut repo = match Self::open(repo_path) {
             Ok(repo) => repo,
             Err(e) if e.message() == "unsupported extension name extensions.worktreeconfig" => {
+                if options.allow_git_shell {
+                    match GitShellStatus::find(repo_path) {
+                        Ok(found) => {
+                            let mut view = RepositoryView::finalize(
+                                repo_path,
+                                Some(found.branch),
+                                found.status,
+                                None,
+                                None,
+                                Vec::with_capacity(0),
+                            )?;
+                            view.ahead = found.ahead;
+                            view.behind = found.behind;
+                            return Ok(Some((view, false)));
+                        }
+                        Err(fallback_error) => error!(
+                            "could not recover status with git binary ({fallback_error}): {}",
+                            repo_path.display()
+                        ),
+                    }
+                }
                 error!(
                     "skipping error ({e}) until upstream libgit2 issue is resolved: https://github.com/libgit2/libgit2/issues/6044"
                 );
@@ -65,65 +251,180 @@ impl RepositoryView {
                     None,
                     Vec::with_capacity(0),
                 )?;
-                return Ok(unknown_report);
+                return Ok(Some((unknown_report, false)));
             }
             Err(e) => return Err(e.into()),
         };
-        let (status, head, remote) = Status::find(&repo)?;
-
-        let submodules = if include_submodules && !repo.is_bare() {
-            SubmoduleView::list(&repo)?
-        } else {
-            Vec::with_capacity(0)
+        if options.skip_bare && repo.is_bare() {
+            debug!("skipping bare repository: {}", repo_path.display());
+            return Ok(None);
+        }
+        // Stashes must be counted before finding the status since counting requires a mutable
+        // reference to the repository.
+        let stashes = Self::get_stashes(&mut repo);
+        let (mut status, head, remote) = match &options.status_cache {
+            Some(status_cache) => {
+                status_cache.find(repo_path, &repo, &options.remote_preference)?
+            }
+            None => Status::find(&repo, &options.remote_preference)?,
         };
 
+        // Flattened submodules are collected as views of their own instead.
+        let submodules =
+            if options.include_submodules && !options.flatten_submodules && !repo.is_bare() {
+                SubmoduleView::list(&repo, &options.remote_preference)?
+            } else {
+                Vec::with_capacity(0)
+            };
+
+        // A detached HEAD is displayed with the abbreviated commit hash. The "HEAD" fallback is only
+        // used for unborn (e.g. empty) repositories.
+        let detached = head.is_some() && repo.head_detached()?;
         let branch = match &head {
-            Some(head) => head
-                .shorthand()
-                .ok_or(anyhow!("full shorthand for Git reference is invalid UTF-8"))?,
-            None => "HEAD",
+            Some(head) if detached => Self::detached_branch(head)?,
+            Some(head) => match head.shorthand() {
+                Some(shorthand) => shorthand.to_string(),
+                // The status cannot be trusted either since finding it relies on the shorthand.
+                None => {
+                    warn!(
+                        "branch name is invalid UTF-8; reporting status as unknown: {}",
+                        repo_path.display()
+                    );
+                    status = Status::Unknown;
+                    String::from_utf8_lossy(head.shorthand_bytes()).into_owned()
+                }
+            },
+            None => "HEAD".to_string(),
         };
 
-        let email = match include_email {
-            true => Self::get_email(&repo),
+        let email = match options.include_email {
+            true => Self::get_email(&repo, options.email_scope),
             false => None,
         };
+        let email_allowed = options.email_allowlist.as_ref().map(|allowlist| {
+            let effective_email = match (options.include_email, options.email_scope) {
+                (true, EmailScope::Effective) => email.clone(),
+                _ => Self::get_email(&repo, EmailScope::Effective),
+            };
+            let allowed = effective_email
+                .as_deref()
+                .is_some_and(|email| allowlist.is_match(email));
+            if !allowed {
+                warn!(
+                    "email ({}) does not match allowlist ({}): {}",
+                    effective_email.as_deref().unwrap_or("none"),
+                    allowlist.as_str(),
+                    repo_path.display()
+                );
+            }
+            allowed
+        });
 
         let url = match remote {
             Some(remote) => remote.url().map(|s| s.to_string()),
             None => None,
         };
-        let url_clone = url.clone();
-        let url_clone2 = url.clone();
-        let binding = url_clone2.unwrap_or("".to_string());
-        let host = binding
-            .split('@')
-            .nth(1)
-            .unwrap_or("")
-            .split(':')
-            .next()
-            .unwrap_or("");
-
-        // Fetch the remote branch.
-        if fetch_remote && url.is_some() && head.is_some() {
-            fetch_remote_locally(&repo, url, host, fetch_password)?;
-        }
+
+        // Fetch the remote branch. Repositories with local changes (i.e. unclean or conflicted) are
+        // skipped if requested since fetching would not change their status.
+        let needs_fetch = options.fetch_remote
+            && !(options.skip_fetch_if_unclean
+                && matches!(status, Status::Unclean | Status::Conflicted))
+            && head.is_some()
+            && !detached
+            && (options.fetch_only.is_empty()
+                || options
+                    .fetch_only
+                    .iter()
+                    .any(|glob| glob.is_match(repo_path)))
+            && url.is_some();
 
         debug!(
             "finalized collector collection for repository_view at path: {}",
             repo_path.display()
         );
-        RepositoryView::finalize(
-            repo_path,
-            Some(branch.to_string()),
-            status,
-            url_clone,
-            email,
-            submodules,
-        )
+        let mut view =
+            RepositoryView::finalize(repo_path, Some(branch), status, url, email, submodules)?;
+        view.stashes = stashes;
+        view.email_allowed = email_allowed;
+        view.last_commit_time = head.as_ref().and_then(Self::get_last_commit_time);
+        if options.include_file_counts && !repo.is_bare() {
+            (view.modified, view.untracked, view.staged) = Self::get_file_counts(&repo);
+        }
+        view.lfs = Self::get_lfs(&repo);
+        view.shallow = repo.is_shallow();
+        if options.include_describe && head.is_some() {
+            view.describe = Self::get_describe(&repo);
+        }
+        if let Some(size_scope) = options.include_size {
+            view.size_bytes = Self::get_size(&repo, size_scope);
+        }
+        view.operation = Self::get_operation(&repo);
+        view.superproject = options.flatten_submodules
+            && repo
+                .submodules()
+                .is_ok_and(|submodules| !submodules.is_empty());
+        // The remote state is collected once the remote has been fetched instead.
+        if !needs_fetch {
+            Self::collect_remote_state(&mut view, &repo, head.as_ref(), options);
+        }
+        Ok(Some((view, needs_fetch)))
+    }
+
+    /// Fetch the remote for the second stage of collection (see [`LocalView::finish()`]) and track
+    /// the time spent fetching. The status found before fetching is only used to decide whether to
+    /// fetch. It is found again so that it agrees with the ahead/behind counts.
+    fn fetch(
+        view: &mut RepositoryView,
+        repo_path: &Path,
+        options: &CollectOptions,
+        fetch: &mut Duration,
+    ) -> Result<()> {
+        let repo = Self::open(repo_path)?;
+        let url = view.url.as_deref().unwrap_or_default();
+        let fetch_started = Instant::now();
+        view.stale_branches = match &options.fetch_cache {
+            Some(cache) if cache.is_fresh(repo_path, url) => {
+                debug!("skipping fetch; cached fetch is still fresh for {url}");
+                None
+            }
+            Some(cache) => {
+                let (fetched, stale_branches) =
+                    fetch_remote_with_timeout(&repo, &view.branch, options)?;
+                if fetched {
+                    cache.record(repo_path, url);
+                }
+                stale_branches
+            }
+            None => fetch_remote_with_timeout(&repo, &view.branch, options)?.1,
+        };
+        *fetch = fetch_started.elapsed();
+        let head = repo.head()?;
+        view.status = view
+            .status
+            .refresh(&repo, Some(&head), &options.remote_preference)?;
+        Self::collect_remote_state(view, &repo, Some(&head), options);
+        Ok(())
     }
 
-    /// Assemble a [`RepositoryView`] with metadata for a given repository.
+    /// Collect the parts of the view that depend on the remote-tracking branches (i.e. the ahead
+    /// and behind counts, the upstream and the default branch of "origin").
+    fn collect_remote_state(
+        view: &mut RepositoryView,
+        repo: &Repository,
+        head: Option<&Reference<'_>>,
+        options: &CollectOptions,
+    ) {
+        if let Some(head) = head {
+            (view.ahead, view.behind) = Self::get_ahead_behind(repo, head, options.first_parent);
+            view.upstream = Self::get_upstream(repo, head);
+        }
+        view.remote_default_branch = Self::get_remote_default_branch(repo);
+    }
+
+    /// Assemble a [`RepositoryView`] with metadata for a given repository. Additional details (e.g.
+    /// ahead and behind counts, stashes, the upstream, file counts and the last commit time) are
+    /// left empty.
     pub fn finalize(
         path: &Path,
         branch: Option<String>,
@@ -166,140 +467,760 @@ impl RepositoryView {
             parent,
             url,
             email,
+            email_allowed: None,
             submodules,
+            ahead: 0,
+            behind: 0,
+            stashes: 0,
+            upstream: None,
+            modified: 0,
+            untracked: 0,
+            staged: 0,
+            last_commit_time: None,
+            describe: None,
+            lfs: false,
+            shallow: false,
+            remote_default_branch: None,
+            size_bytes: None,
+            stale_branches: None,
+            operation: None,
+            superproject: false,
+        })
+    }
+
+    /// Open the repository at the given path like `git` would in the same directory. Parent
+    /// directories are never searched since the path is expected to be a repository. If the path
+    /// is the working tree named by `$GIT_WORK_TREE`, the repository is opened from the
+    /// environment instead (i.e. `$GIT_DIR` and `$GIT_WORK_TREE` are honored). Worktrees (i.e.
+    /// those whose `.git` is a file) are opened through their link to the main repository.
+    fn open(repo_path: &Path) -> Result<Repository, git2::Error> {
+        if Self::is_env_work_tree(repo_path) {
+            debug!("opening repository from $GIT_DIR and $GIT_WORK_TREE");
+            let repo = Repository::open_from_env()?;
+            // Unlike git, libgit2 ignores "$GIT_WORK_TREE" for repositories with "core.bare" set.
+            if repo.is_bare() {
+                repo.set_workdir(repo_path, false)?;
+            }
+            return Ok(repo);
+        }
+        Repository::open_ext(
+            repo_path,
+            RepositoryOpenFlags::NO_SEARCH,
+            iter::empty::<&OsStr>(),
+        )
+    }
+
+    /// Check if the path is the working tree named by `$GIT_WORK_TREE` (e.g. for a home directory
+    /// tracked by a bare repository elsewhere), which requires `$GIT_DIR` to be set as well.
+    pub(crate) fn is_env_work_tree(path: &Path) -> bool {
+        if env::var_os("GIT_DIR").is_none() {
+            return false;
+        }
+        env::var_os("GIT_WORK_TREE").is_some_and(|work_tree| {
+            match (fs::canonicalize(work_tree), fs::canonicalize(path)) {
+                (Ok(work_tree), Ok(path)) => work_tree == path,
+                _ => false,
+            }
         })
     }
 
-    /// Find the "user.email" value in the local or global Git config. The
-    /// [`Repository::config()`] method will look for a local config first and fallback to
-    /// global, as needed. Absorb and log any and all errors as the email field is non-critical to
+    /// Find the time of the commit that HEAD points to. Absorb and log any and all errors as the
+    /// time is non-critical to the final results.
+    fn get_last_commit_time(head: &Reference<'_>) -> Option<i64> {
+        match head.peel_to_commit() {
+            Ok(commit) => Some(commit.time().seconds()),
+            Err(e) => {
+                trace!("ignored error: {e}");
+                None
+            }
+        }
+    }
+
+    /// Check if the repository uses Git LFS without requiring the "git-lfs" binary. Absorb and log
+    /// any and all errors as the result is non-critical to the final results.
+    fn get_lfs(repository: &Repository) -> bool {
+        if repository.path().join("lfs").is_dir() {
+            return true;
+        }
+        let Some(workdir) = repository.workdir() else {
+            return false;
+        };
+        match fs::read_to_string(workdir.join(".gitattributes")) {
+            Ok(attributes) => attributes.lines().any(|line| {
+                !line.trim_start().starts_with('#')
+                    && line
+                        .split_whitespace()
+                        .any(|attribute| attribute == "filter=lfs")
+            }),
+            Err(e) => {
+                trace!("ignored error: {e}");
+                false
+            }
+        }
+    }
+
+    /// Compute the on-disk size of the repository for the given scope. Symlinks are not followed.
+    /// Absorb and log any and all errors (e.g. permission errors) as the size is non-critical to
     /// the final results.
-    fn get_email(repository: &Repository) -> Option<String> {
-        let config = match repository.config() {
-            Ok(v) => v,
+    fn get_size(repository: &Repository, size_scope: SizeScope) -> Option<u64> {
+        let path = match (size_scope, repository.workdir()) {
+            (SizeScope::Worktree, Some(workdir)) => workdir,
+            _ => repository.path(),
+        };
+        match directory_size(path) {
+            Ok(size) => Some(size),
+            Err(e) => {
+                trace!("ignored error: {e}");
+                None
+            }
+        }
+    }
+
+    /// Describe HEAD relative to the most recent tag reachable from it (lightweight tags included).
+    /// Absorb and log any and all errors as the description is non-critical to the final results.
+    fn get_describe(repository: &Repository) -> Option<String> {
+        let mut describe_options = DescribeOptions::new();
+        describe_options.describe_tags();
+        let found = repository
+            .describe(&describe_options)
+            .and_then(|describe| describe.format(None));
+        match found {
+            Ok(describe) => Some(describe),
+            Err(e) => {
+                trace!("ignored error: {e}");
+                None
+            }
+        }
+    }
+
+    /// Count the modified, untracked and staged files (in that order). A file with both staged
+    /// and unstaged changes is counted as both modified and staged. Absorb and log any and all
+    /// errors as the counts are non-critical to the final results.
+    fn get_file_counts(repository: &Repository) -> (usize, usize, usize) {
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false);
+        let statuses = match repository.statuses(Some(&mut opts)) {
+            Ok(statuses) => statuses,
+            Err(e) => {
+                trace!("ignored error: {e}");
+                return (0, 0, 0);
+            }
+        };
+
+        let modified_flags = git2::Status::WT_MODIFIED
+            | git2::Status::WT_DELETED
+            | git2::Status::WT_RENAMED
+            | git2::Status::WT_TYPECHANGE;
+        let staged_flags = git2::Status::INDEX_NEW
+            | git2::Status::INDEX_MODIFIED
+            | git2::Status::INDEX_DELETED
+            | git2::Status::INDEX_RENAMED
+            | git2::Status::INDEX_TYPECHANGE;
+
+        let (mut modified, mut untracked, mut staged) = (0, 0, 0);
+        for entry in statuses.iter() {
+            let status = entry.status();
+            if status.intersects(modified_flags) {
+                modified += 1;
+            }
+            if status.contains(git2::Status::WT_NEW) {
+                untracked += 1;
+            }
+            if status.intersects(staged_flags) {
+                staged += 1;
+            }
+        }
+        (modified, untracked, staged)
+    }
+
+    /// Count the number of stashed changes. Absorb and log any and all errors as the stash count
+    /// is non-critical to the final results.
+    fn get_stashes(repository: &mut Repository) -> usize {
+        let mut stashes = 0;
+        if let Err(e) = repository.stash_foreach(|_, _, _| {
+            stashes += 1;
+            true
+        }) {
+            trace!("ignored error: {e}");
+        }
+        stashes
+    }
+
+    /// Describe a detached HEAD as "detached@<short-sha>".
+    fn detached_branch(head: &Reference<'_>) -> Result<String> {
+        let short_id = head.peel_to_commit()?.as_object().short_id()?;
+        Ok(format!(
+            "detached@{}",
+            short_id
+                .as_str()
+                .ok_or(anyhow!("short id for HEAD commit is invalid UTF-8"))?
+        ))
+    }
+
+    /// Find the number of commits the current branch is ahead and behind its upstream tracking
+    /// branch. Absorb and log any and all errors (including a missing upstream) as the counts are
+    /// non-critical to the final results. If only following first parents, commits that were
+    /// merged into either branch (i.e. via their second parents) are not counted.
+    fn get_ahead_behind(
+        repository: &Repository,
+        head: &Reference<'_>,
+        first_parent: bool,
+    ) -> (usize, usize) {
+        match Self::find_ahead_behind(repository, head, first_parent) {
+            Ok(ahead_behind) => ahead_behind,
+            Err(e) => {
+                trace!("ignored error: {e}");
+                (0, 0)
+            }
+        }
+    }
+
+    /// Find the name of the upstream tracking branch of the current branch (e.g. "origin/main").
+    /// Absorb and log any and all errors (including a missing upstream) as the name is
+    /// non-critical to the final results.
+    fn get_upstream(repository: &Repository, head: &Reference<'_>) -> Option<String> {
+        let shorthand = head.shorthand().filter(|_| head.is_branch())?;
+        let upstream = match repository
+            .find_branch(shorthand, BranchType::Local)
+            .and_then(|branch| branch.upstream())
+        {
+            Ok(upstream) => upstream,
             Err(e) => {
                 trace!("ignored error: {e}");
                 return None;
             }
         };
-        let mut entries = match config.entries(None) {
-            Ok(v) => v,
+        match upstream.name() {
+            Ok(name) => name.map(|name| name.to_string()),
+            Err(e) => {
+                trace!("ignored error: {e}");
+                None
+            }
+        }
+    }
+
+    /// Find the default branch of "origin" from where "origin/HEAD" points. Absorb and log any and
+    /// all errors as the default branch is non-critical to the final results.
+    fn get_remote_default_branch(repository: &Repository) -> Option<String> {
+        let reference = match repository.find_reference("refs/remotes/origin/HEAD") {
+            Ok(reference) => reference,
             Err(e) => {
                 trace!("ignored error: {e}");
                 return None;
             }
         };
+        reference
+            .symbolic_target()?
+            .strip_prefix("refs/remotes/origin/")
+            .map(str::to_string)
+    }
 
-        // Greedily find our "user.email" value. Return the first result found.
-        while let Some(entry) = entries.next() {
-            match entry {
-                Ok(entry) => {
-                    if let Some(name) = entry.name() {
-                        if name == "user.email" {
-                            if let Some(value) = entry.value() {
-                                return Some(value.to_string());
-                            }
-                        }
-                    }
+    /// Find the operation in progress (e.g. a rebase that was never finished or aborted). The
+    /// value will be `None` if the repository is in a clean state.
+    fn get_operation(repository: &Repository) -> Option<String> {
+        let operation = match repository.state() {
+            RepositoryState::Clean => return None,
+            RepositoryState::Merge => "merge",
+            RepositoryState::Revert => "revert",
+            RepositoryState::RevertSequence => "revert-sequence",
+            RepositoryState::CherryPick => "cherry-pick",
+            RepositoryState::CherryPickSequence => "cherry-pick-sequence",
+            RepositoryState::Bisect => "bisect",
+            RepositoryState::Rebase => "rebase",
+            RepositoryState::RebaseInteractive => "rebase-interactive",
+            RepositoryState::RebaseMerge => "rebase-merge",
+            RepositoryState::ApplyMailbox => "apply-mailbox",
+            RepositoryState::ApplyMailboxOrRebase => "apply-mailbox-or-rebase",
+        };
+        Some(operation.to_string())
+    }
+
+    fn find_ahead_behind(
+        repository: &Repository,
+        head: &Reference<'_>,
+        first_parent: bool,
+    ) -> Result<(usize, usize), git2::Error> {
+        let Some(shorthand) = head.shorthand().filter(|_| head.is_branch()) else {
+            return Ok((0, 0));
+        };
+        let upstream = repository
+            .find_branch(shorthand, BranchType::Local)?
+            .upstream()?;
+        let local = head.peel_to_commit()?.id();
+        let upstream = upstream.get().peel_to_commit()?.id();
+        match first_parent {
+            true => Ok((
+                Self::count_first_parents(repository, local, upstream)?,
+                Self::count_first_parents(repository, upstream, local)?,
+            )),
+            false => repository.graph_ahead_behind(local, upstream),
+        }
+    }
+
+    /// Count the commits on the first-parent line of `from` that are not reachable from `hidden`,
+    /// which matches "git rev-list --first-parent --count hidden..from".
+    fn count_first_parents(
+        repository: &Repository,
+        from: Oid,
+        hidden: Oid,
+    ) -> Result<usize, git2::Error> {
+        let mut revwalk = repository.revwalk()?;
+        revwalk.simplify_first_parent()?;
+        revwalk.push(from)?;
+        revwalk.hide(hidden)?;
+        let mut count = 0;
+        for oid in revwalk {
+            oid?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Find the "user.email" value in the Git config for the given [`EmailScope`]. For the
+    /// effective scope, the config from [`Repository::config()`] prefers the local value and falls
+    /// back to global, as needed. Absorb and log any and all errors as the email field is
+    /// non-critical to the final results.
+    fn get_email(repository: &Repository, scope: EmailScope) -> Option<String> {
+        let config = match repository.config() {
+            Ok(v) => v,
+            Err(e) => {
+                trace!("ignored error: {e}");
+                return None;
+            }
+        };
+        let config = match scope {
+            EmailScope::Effective => config,
+            EmailScope::GlobalOnly => match config.open_level(ConfigLevel::Global) {
+                Ok(v) => v,
+                Err(e) => {
+                    trace!("ignored error: {e}");
+                    return None;
+                }
+            },
+            EmailScope::LocalOnly => match config.open_level(ConfigLevel::Local) {
+                Ok(v) => v,
+                Err(e) => {
+                    trace!("ignored error: {e}");
+                    return None;
                 }
-                Err(e) => debug!("ignored error: {e}"),
+            },
+        };
+        // The value with the highest priority (e.g. local over global) is used if there are
+        // multiple values within the scope.
+        match config.get_string("user.email") {
+            Ok(value) => Some(value),
+            Err(e) => {
+                trace!("ignored error: {e}");
+                None
             }
         }
-        None
     }
 }
 
+/// Sum the (apparent) sizes of all files in the directory and its descendants without following
+/// symlinks.
+fn directory_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += match metadata.is_dir() {
+            true => directory_size(&entry.path())?,
+            false => metadata.len(),
+        };
+    }
+    Ok(size)
+}
+
+/// The fetch options from [`CollectOptions`], which are owned so that they can be moved to a worker
+/// thread.
+#[derive(Debug, Clone)]
+struct FetchSettings {
+    password: String,
+    passphrase_prompt: Option<PassphrasePrompt>,
+    all_remotes: bool,
+    transport: Option<Transport>,
+    retries: usize,
+    find_stale_branches: bool,
+    remote_preference: Vec<String>,
+    error_log: Option<ErrorLog>,
+    origin_head_log: Option<OriginHeadLog>,
+}
+
+impl FetchSettings {
+    fn new(options: &CollectOptions) -> Self {
+        Self {
+            password: options.fetch_password.clone(),
+            passphrase_prompt: options.passphrase_prompt.clone(),
+            all_remotes: options.fetch_all_remotes,
+            transport: options.fetch_transport,
+            retries: options.fetch_retries,
+            find_stale_branches: options.find_stale_branches,
+            remote_preference: options.remote_preference.clone(),
+            error_log: options.error_log.clone(),
+            origin_head_log: options.origin_head_log.clone(),
+        }
+    }
+}
+
+/// Run [`fetch_remote_locally`] on the current thread (i.e. on the fetch thread pool, if any, which
+/// runs the second stage of collection) or on a worker thread if a fetch timeout is set, which
+/// allows us to abandon a fetch that does not complete in time (e.g. when the remote is
+/// unreachable). The worker opens its own handle to the repository since [`Repository`] cannot be
+/// shared across threads. Waiting for a fetch concurrency permit (if limited) counts against the
+/// timeout. The permit is held by the current thread rather than the worker, so abandoned fetches
+/// neither affect the (local) results nor count against the limit. Returns whether or not the
+/// fetch succeeded and the number of stale remote-tracking branches (if requested).
+fn fetch_remote_with_timeout(
+    repo: &Repository,
+    branch: &str,
+    options: &CollectOptions,
+) -> Result<(bool, Option<usize>)> {
+    let settings = FetchSettings::new(options);
+    let deadline = options
+        .fetch_timeout
+        .map(|timeout| Instant::now() + timeout);
+    let _permit = match (&options.fetch_limiter, options.fetch_timeout) {
+        (Some(fetch_limiter), Some(timeout)) => match fetch_limiter.acquire_timeout(timeout) {
+            Some(permit) => Some(permit),
+            None => return Ok(abandon_fetch(repo, options, "waiting for other fetches")),
+        },
+        (Some(fetch_limiter), None) => Some(fetch_limiter.acquire()),
+        (None, _) => None,
+    };
+    let Some(deadline) = deadline else {
+        return fetch_remote_locally(repo, branch, &settings);
+    };
+
+    let repo_path = repo.path().to_path_buf();
+    // Logs from the worker are attributed to the same repository (the working tree, if any).
+    let scope_path: PathBuf = repo.workdir().unwrap_or(repo.path()).components().collect();
+    let branch = branch.to_string();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _scope = RepositoryScope::enter(&scope_path);
+        let result = Repository::open(&repo_path)
+            .map_err(Into::into)
+            .and_then(|repo| fetch_remote_locally(&repo, &branch, &settings));
+        // The receiver will be gone if the fetch was abandoned, so we ignore the send result.
+        let _ = sender.send(result);
+    });
+    match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => Ok(abandon_fetch(repo, options, "fetching")),
+        Err(RecvTimeoutError::Disconnected) => Err(anyhow!(
+            "fetch thread exited without a result for: {}",
+            repo.path().display()
+        )),
+    }
+}
+
+/// Log (and record in the error log, if any) that the fetch timed out while doing the given
+/// activity (e.g. "fetching"), so that the local status is used instead. Returns the result of
+/// the abandoned fetch.
+fn abandon_fetch(
+    repo: &Repository,
+    options: &CollectOptions,
+    activity: &str,
+) -> (bool, Option<usize>) {
+    let timeout = options.fetch_timeout.unwrap_or_default().as_secs();
+    debug!(
+        "abandoned fetch after {timeout}s ({activity}); continuing with local status for: {}",
+        repo.path().display()
+    );
+    if let Some(error_log) = &options.error_log {
+        let scope_path: PathBuf = repo.workdir().unwrap_or(repo.path()).components().collect();
+        error_log.record(
+            &scope_path,
+            format!("abandoned fetch after {timeout}s ({activity})"),
+        );
+    }
+    (false, None)
+}
+
+/// Fetch the current branch from the preferred remote (see [`Status::find_remote()`]). If all
+/// remotes are fetched, each remote is fetched using its configured refspecs instead since the
+/// current branch might not exist on every remote. The same applies to "origin" if its HEAD is
+/// refreshed, since the default branch must be fetched as well. Failures are logged (and recorded
+/// in the error log, if any) and tolerated for each remote individually. Returns whether or not
+/// every fetch succeeded and, if requested, the number of remote-tracking branches that no longer
+/// exist on the remotes that were fetched (`None` if no remote was fetched).
 fn fetch_remote_locally(
     repo: &Repository,
-    url: Option<String>,
-    host: &str,
-    fetch_password: String,
-) -> Result<()> {
-    let (remote, _) = match repo.find_remote("origin") {
-        Ok(origin) => (Some(origin), Some("origin".to_string())),
-        Err(e) if e.code() == ErrorCode::NotFound => Status::choose_remote_greedily(&repo)?,
-        Err(e) => return Err(e.into()),
+    branch: &str,
+    settings: &FetchSettings,
+) -> Result<(bool, Option<usize>)> {
+    let remotes = match settings.all_remotes {
+        true => repo
+            .remotes()?
+            .iter()
+            .flatten()
+            .map(|name| repo.find_remote(name))
+            .collect::<Result<Vec<Remote<'_>>, _>>()?,
+        false => {
+            let (remote, _) = Status::find_remote(repo, &settings.remote_preference)?;
+            remote.into_iter().collect()
+        }
+    };
+    if remotes.is_empty() {
+        debug!(
+            "skipping fetch; no remote found for: {}",
+            repo.path().display()
+        );
+        return Ok((false, None));
+    }
+
+    // Logs and errors from the fetch are attributed to the working tree, if any.
+    let scope_path: PathBuf = repo.workdir().unwrap_or(repo.path()).components().collect();
+    let mut all_fetched = true;
+    let mut stale_branches = None;
+    for mut remote in remotes {
+        let refresh_origin_head = settings
+            .origin_head_log
+            .as_ref()
+            .filter(|_| remote.name() == Some(origin_head::ORIGIN));
+        let refspecs: &[&str] = match settings.all_remotes || refresh_origin_head.is_some() {
+            true => &[],
+            false => &[branch],
+        };
+        let fetched = match fetch_remote(
+            &mut remote,
+            refspecs,
+            &settings.password,
+            settings.passphrase_prompt.as_ref(),
+            settings.transport,
+            settings.retries,
+        ) {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                debug!("assuming unmerged; {e}");
+                if let Some(error_log) = &settings.error_log {
+                    error_log.record(&scope_path, e.to_string());
+                }
+                false
+            }
+        };
+        if fetched
+            && let Some(origin_head_log) = refresh_origin_head
+            && let Err(e) = origin_head_log.refresh(&scope_path, repo, &remote)
+        {
+            debug!("{e:#} (ignored error)");
+            if let Some(error_log) = &settings.error_log {
+                error_log.record(&scope_path, format!("{e:#}"));
+            }
+        }
+        all_fetched &= fetched;
+        if fetched
+            && settings.find_stale_branches
+            && let Some(count) = count_stale_branches(repo, &remote)
+        {
+            *stale_branches.get_or_insert(0) += count;
+        }
+    }
+    Ok((all_fetched, stale_branches))
+}
+
+/// Count the remote-tracking branches of a fetched remote whose branch was not advertised by the
+/// remote during the fetch (i.e. `git fetch --prune` would delete them). The value will be `None`
+/// if the references cannot be read.
+fn count_stale_branches(repo: &Repository, remote: &Remote<'_>) -> Option<usize> {
+    let name = remote.name()?;
+    let advertised = match remote.list() {
+        Ok(heads) => heads
+            .iter()
+            .filter_map(|head| head.name().strip_prefix("refs/heads/"))
+            .map(str::to_string)
+            .collect::<HashSet<String>>(),
+        Err(e) => {
+            trace!("ignored error: {e}");
+            return None;
+        }
     };
-    let mut some_remote = remote.unwrap();
-    let current_head = match repo.head() {
-        Ok(head) => Some(head),
-        Err(ref e) if e.code() == ErrorCode::UnbornBranch || e.code() == ErrorCode::NotFound => {
-            None
+    let prefix = format!("refs/remotes/{name}/");
+    let references = match repo.references_glob(&format!("{prefix}*")) {
+        Ok(references) => references,
+        Err(e) => {
+            trace!("ignored error: {e}");
+            return None;
         }
-        Err(e) => return Err(e.into()),
     };
-    let some_head = current_head.unwrap();
-    let short_remote_branch_name = some_head.shorthand().unwrap();
-    let mut callbacks = RemoteCallbacks::new();
-    let mut fetch_options = FetchOptions::new();
-    let remote_url = url.unwrap().clone();
-    let is_https = remote_url.starts_with("https://");
-    if !is_https {
-        debug!("fetching remote {} with ssh key", remote_url);
-        callbacks.credentials(|_url, username_from_url, _allowed_types| {
-            let host_to_check = host.to_string();
-            let default_config_path = std::env::var("HOME").unwrap() + "/.ssh/config";
-            let mut reader = BufReader::new(
-                File::open(default_config_path).expect("Could not open configuration file"),
-            );
+    let count = references
+        .flatten()
+        .filter_map(|reference| {
+            reference
+                .name()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .map(str::to_string)
+        })
+        .filter(|branch| branch != "HEAD" && !advertised.contains(branch))
+        .count();
+    debug!("found {count} stale remote-tracking branches for remote: {name}");
+    Some(count)
+}
+
+/// Fetch the given refspecs from a single remote (the remote's configured refspecs are used if
+/// empty). If authenticating with an SSH key fails and a prompt is provided, the fetch is retried
+/// with the passphrase from the prompt. Transient failures (e.g. network errors) are retried up to
+/// the given number of times with exponential backoff, but authentication failures are not.
+/// Remotes that do not use the given transport (if any) are skipped. Returns whether or not the
+/// remote was fetched (i.e. `false` if it was skipped) or an error if the fetch failed.
+fn fetch_remote(
+    remote: &mut Remote<'_>,
+    refspecs: &[&str],
+    fetch_password: &str,
+    passphrase_prompt: Option<&PassphrasePrompt>,
+    transport: Option<Transport>,
+    retries: usize,
+) -> Result<bool> {
+    let Some(url) = remote.url().map(|s| s.to_string()) else {
+        debug!("skipping fetch; remote url is invalid UTF-8");
+        return Ok(false);
+    };
+    let is_https = url.starts_with("https://");
+    let remote_transport = match is_https {
+        true => Transport::Https,
+        false => Transport::Ssh,
+    };
+    if let Some(transport) = transport
+        && transport != remote_transport
+    {
+        debug!("skipping fetch; only fetching remotes using {transport:?}: {url}");
+        return Ok(false);
+    }
 
-            let config = SshConfig::default()
-                .parse(&mut reader, ParseRule::STRICT)
-                .expect("Failed to parse configuration");
-
-            // Get the host from the remote url that is in format "git@host:owner/repo"
-            // query() returns default params when there's no rule for the host
-            let params = config.query(host_to_check);
-
-            // Compose Default key by combining env variable $HOME and "/.ssh/config"
-            let default_key_path = std::env::var("HOME").unwrap() + "/.ssh/id_rsa";
-            let mut ssh_key_path = default_key_path.as_str();
-            let default_key_file = PathBuf::from(ssh_key_path);
-            // default params from ssh config
-
-            // Get the ssh_key_path as string from the first entry from config "IdentityFile" if exists
-            let binding = params
-                .identity_file
-                .or(Some(vec![default_key_file]))
-                .to_owned()
-                .unwrap()
-                .to_owned();
-            if let Some(identity_file) = binding.first() {
-                ssh_key_path = identity_file.to_str().unwrap();
+    let host = credentials::ssh_host(&url);
+
+    // The home directory is resolved the same way as for config file lookup (i.e. it works on
+    // Windows and does not panic when "HOME" is unset).
+    let home = match is_https {
+        true => None,
+        false => match user_dirs::home_dir() {
+            Ok(home) => Some(home),
+            Err(e) => {
+                return Err(anyhow!(
+                    "could not fetch from {url}; could not find home directory: {e}"
+                ));
             }
-            // in case there are multiple entries, get the first one
-            debug!("ssh_key_path: {}", ssh_key_path);
-            let pass = if fetch_password.is_empty() {
-                None
-            } else {
-                Some(fetch_password.as_str())
-            };
+        },
+    };
 
-            return Cred::ssh_key(
-                username_from_url.unwrap(),
-                None,
-                Path::new(ssh_key_path),
-                pass,
-            );
+    let mut passphrase = fetch_password.to_string();
+    let mut attempt = 0;
+    let mut retried = 0;
+    let mut backoff = FETCH_RETRY_BACKOFF;
+    loop {
+        attempt += 1;
+        debug!("fetching {refspecs:?} from {url} (attempt {attempt})");
+        match fetch_remote_once(remote, refspecs, &url, host, home.as_deref(), &passphrase) {
+            Ok(()) => return Ok(true),
+            Err(e)
+                if home.is_some()
+                    && (e.code() == ErrorCode::Auth || e.class() == ErrorClass::Ssh)
+                    && let Some(next) = passphrase_prompt.and_then(|p| p.next(&passphrase)) =>
+            {
+                debug!("retrying fetch from {url} with prompted passphrase (ignored error: {e})");
+                passphrase = next;
+            }
+            Err(e) if retried < retries && is_transient(&e) => {
+                debug!(
+                    "retrying fetch from {url} in {}ms (ignored error: {e})",
+                    backoff.as_millis()
+                );
+                thread::sleep(backoff);
+                retried += 1;
+                backoff = backoff.saturating_mul(2);
+            }
+            Err(e) => {
+                return Err(anyhow!("could not fetch {refspecs:?} from {url}: {e}"));
+            }
+        }
+    }
+}
+
+/// Check if a fetch error might not occur again when retrying (i.e. it is not an authentication
+/// failure).
+fn is_transient(error: &git2::Error) -> bool {
+    error.code() != ErrorCode::Auth
+        && error.code() != ErrorCode::Certificate
+        && matches!(
+            error.class(),
+            ErrorClass::Net | ErrorClass::Os | ErrorClass::Http
+        )
+}
+
+/// Attempt a single fetch from the remote. SSH credentials are used if the home directory is
+/// provided (i.e. for non-HTTPS remotes).
+fn fetch_remote_once(
+    remote: &mut Remote<'_>,
+    refspecs: &[&str],
+    url: &str,
+    host: &str,
+    home: Option<&Path>,
+    passphrase: &str,
+) -> Result<(), git2::Error> {
+    let ssh_credentials = home.map(|home| SshCredentials::new(home, host, passphrase));
+    let mut callbacks = RemoteCallbacks::new();
+    let mut fetch_options = FetchOptions::new();
+    if let Some(ssh_credentials) = &ssh_credentials {
+        debug!("fetching remote {url} with ssh agent or key");
+        callbacks.credentials(|_url, username_from_url, allowed_types| {
+            ssh_credentials.next(username_from_url, allowed_types)
+        });
+    } else if let Some(https_credentials) = HttpsCredentials::from_env() {
+        debug!("fetching remote {url} with https token");
+        callbacks.credentials(move |_url, username_from_url, _allowed_types| {
+            https_credentials.next(username_from_url)
         });
+    } else {
+        debug!("fetching remote {url} anonymously; no https token found");
     }
     fetch_options.remote_callbacks(callbacks);
-    Ok(
-        if let Err(e) =
-            some_remote.fetch(&[&short_remote_branch_name], Some(&mut fetch_options), None)
-        {
-            let remote_url = some_remote.url().unwrap_or("unknown");
-            debug!(
-                "assuming unmerged; could not fetch remote branch {} from {} (ignored error: {})",
-                short_remote_branch_name, remote_url, e
-            );
-            // return Ok(false);
-        } else {
-            debug!(
-                "fetched remote branch {} from {}",
-                short_remote_branch_name, remote_url
+    remote.fetch(refspecs, Some(&mut fetch_options), None)?;
+    debug!("fetched {refspecs:?} from {url}");
+    if let Some(key) = ssh_credentials.as_ref().and_then(|c| c.last_offered()) {
+        debug!("authenticated with: {key}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository_view::fetch_limiter::FetchLimiter;
+    use pretty_assertions::assert_eq;
+    use std::net::TcpListener;
+    use tempfile::tempdir;
+
+    #[allow(clippy::panic_in_result_fn)]
+    #[test]
+    fn abandoned_fetch_releases_permit() -> Result<()> {
+        // The remote accepts connections but never responds, so every fetch hangs.
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/hung.git", listener.local_addr()?);
+        thread::spawn(move || {
+            let connections: Vec<_> = listener.incoming().collect();
+            drop(connections);
+        });
+
+        let root = tempdir()?;
+        let options = CollectOptions {
+            fetch_timeout: Some(Duration::from_millis(200)),
+            fetch_limiter: Some(FetchLimiter::new(1)),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        for name in ["one", "two", "three"] {
+            let repo = Repository::init(root.path().join(name))?;
+            repo.remote("origin", &url)?;
+            assert_eq!(
+                fetch_remote_with_timeout(&repo, "master", &options)?,
+                (false, None)
             );
-            // return Ok(true);
-        },
-    )
+        }
+        // Each fetch is abandoned after its own timeout rather than waiting for the previous one.
+        assert!(started.elapsed() < Duration::from_secs(2));
+        Ok(())
+    }
 }
@@ -0,0 +1,76 @@
+//! This module contains [`CacheFile`], which persists the entries of a cache across runs.
+
+use std::fs;
+use std::path::PathBuf;
+
+use log::debug;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// A JSON file in `$XDG_CACHE_HOME/gfold` containing the entries of a cache. All errors are logged
+/// and ignored, so a cache always starts out empty if its file cannot be read.
+#[derive(Debug)]
+pub(super) struct CacheFile {
+    path: Option<PathBuf>,
+    /// The name of the cache (e.g. "fetch"), which is used in logs.
+    name: &'static str,
+}
+
+impl CacheFile {
+    /// Find the file with the given name in `$XDG_CACHE_HOME/gfold`. Nothing is read or written if
+    /// the cache directory cannot be found.
+    pub(super) fn new(name: &'static str, file_name: &str) -> Self {
+        let path = match user_dirs::cache_dir() {
+            Ok(cache_dir) => Some(cache_dir.join("gfold").join(file_name)),
+            Err(e) => {
+                debug!(
+                    "{name} cache disabled; could not find cache directory (ignored error: {e})"
+                );
+                None
+            }
+        };
+        Self { path, name }
+    }
+
+    /// Read the entries. Missing or unreadable files are treated as empty.
+    pub(super) fn read<T: DeserializeOwned>(&self) -> Vec<T> {
+        let Some(path) = &self.path else {
+            return Vec::new();
+        };
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                debug!(
+                    "starting with empty {} cache (ignored error: {e})",
+                    self.name
+                );
+                return Vec::new();
+            }
+        };
+        serde_json::from_str(&contents).unwrap_or_else(|e| {
+            debug!(
+                "starting with empty {} cache (ignored error: {e})",
+                self.name
+            );
+            Vec::new()
+        })
+    }
+
+    /// Write the entries, creating the cache directory if needed.
+    pub(super) fn write<T: Serialize>(&self, entries: &[T]) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let result = serde_json::to_string(entries)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                Ok(fs::write(path, contents)?)
+            });
+        if let Err(e) = result {
+            debug!("could not write {} cache (ignored error: {e})", self.name);
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! This module contains the credentials offered when fetching from a remote.
+
+use std::cell::Cell;
+use std::env;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use git2::{Cred, CredentialType};
+use log::{debug, error};
+use ssh2_config::{ParseRule, SshConfig};
+
+/// The environment variables checked (in order) for a personal access token when fetching a remote
+/// over HTTPS.
+const HTTPS_TOKEN_ENV_VARS: [&str; 2] = ["GFOLD_HTTPS_TOKEN", "GITHUB_TOKEN"];
+
+/// The username paired with the HTTPS token when the remote URL does not contain one (e.g.
+/// `https://github.com/owner/repo` rather than `https://oauth2@gitlab.com/owner/repo`). GitHub
+/// and Gitea/Forgejo accept any non-empty username alongside a token, so this default works
+/// against them out of the box. GitLab expects `oauth2` and Bitbucket expects `x-token-auth`,
+/// which must be provided via the URL.
+const HTTPS_TOKEN_DEFAULT_USERNAME: &str = "x-access-token";
+
+/// The environment variable checked for the path to the SSH config file. If unset, the default
+/// path (`$HOME/.ssh/config`) is used.
+const SSH_CONFIG_ENV_VAR: &str = "GFOLD_SSH_CONFIG";
+
+/// The environment variable checked for the path to an SSH key file. If set, the key is the only
+/// credential offered when fetching over SSH (i.e. neither the SSH agent nor the key files from the
+/// SSH config are offered).
+const SSH_KEY_ENV_VAR: &str = "GFOLD_SSH_KEY";
+
+/// The environment variable checked for the username used when fetching over SSH. If set, it takes
+/// precedence over both the username in the remote URL and the "User" entry from the SSH config.
+const SSH_USER_ENV_VAR: &str = "GFOLD_SSH_USER";
+
+/// The key files (within `$HOME/.ssh`) tried when the SSH config does not provide any
+/// "IdentityFile" entries for a host.
+const SSH_DEFAULT_KEY_FILES: [&str; 3] = ["id_rsa", "id_ecdsa", "id_ed25519"];
+
+/// Credentials for fetching over HTTPS with a personal access token.
+pub(crate) struct HttpsCredentials {
+    token: String,
+    attempted: Cell<bool>,
+}
+
+impl HttpsCredentials {
+    /// Find the first non-empty token from [`HTTPS_TOKEN_ENV_VARS`]. If none are found, the fetch
+    /// should be performed anonymously.
+    pub(crate) fn from_env() -> Option<Self> {
+        HTTPS_TOKEN_ENV_VARS
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|token| !token.is_empty())
+            .map(|token| Self {
+                token,
+                attempted: Cell::new(false),
+            })
+    }
+
+    /// Offer the token for the credentials callback. The token is only offered once. Otherwise,
+    /// libgit2 will keep asking for credentials if the token is rejected.
+    pub(crate) fn next(&self, username_from_url: Option<&str>) -> Result<Cred, git2::Error> {
+        if self.attempted.replace(true) {
+            return Err(git2::Error::from_str("https token was rejected"));
+        }
+        Cred::userpass_plaintext(
+            username_from_url.unwrap_or(HTTPS_TOKEN_DEFAULT_USERNAME),
+            &self.token,
+        )
+    }
+}
+
+/// Credentials for fetching over SSH. The SSH agent is offered first (if available). Then, each key
+/// file is offered once (in order) until one of them authenticates, which matches how the real
+/// SSH client behaves.
+pub(crate) struct SshCredentials<'a> {
+    keys: Vec<PathBuf>,
+    user: Option<String>,
+    user_from_env: bool,
+    passphrase: Option<&'a str>,
+    use_agent: bool,
+    agent_attempted: Cell<bool>,
+    attempts: Cell<usize>,
+}
+
+impl<'a> SshCredentials<'a> {
+    /// Collect the key files and the username for the given host. If a key is provided via
+    /// [`SSH_KEY_ENV_VAR`], it is the only credential offered. Otherwise, all "IdentityFile"
+    /// entries from the SSH config are used if they exist or the [`SSH_DEFAULT_KEY_FILES`] are
+    /// used. The "User" entry from the SSH config is used if the remote URL has no username.
+    pub(crate) fn new(home: &Path, host: &str, passphrase: &'a str) -> Self {
+        // query() returns default params when there's no rule for the host
+        let params = ssh_config(home).query(host);
+        let (user, user_from_env) = match env::var(SSH_USER_ENV_VAR) {
+            Ok(user) if !user.is_empty() => {
+                debug!("using ssh username from ${SSH_USER_ENV_VAR}: {user}");
+                (Some(user), true)
+            }
+            _ => (params.user, false),
+        };
+        let key_override = match ssh_key_override() {
+            Ok(key_override) => key_override,
+            Err(e) => {
+                error!("{e}");
+                return Self {
+                    keys: Vec::new(),
+                    user,
+                    user_from_env,
+                    passphrase: None,
+                    use_agent: false,
+                    agent_attempted: Cell::new(false),
+                    attempts: Cell::new(0),
+                };
+            }
+        };
+        let use_agent = key_override.is_none();
+        let keys = match key_override {
+            Some(key) => {
+                debug!("using ssh key from ${SSH_KEY_ENV_VAR}: {}", key.display());
+                vec![key]
+            }
+            None => match params.identity_file {
+                Some(identity_files) if !identity_files.is_empty() => identity_files,
+                _ => SSH_DEFAULT_KEY_FILES
+                    .iter()
+                    .map(|key_file| home.join(".ssh").join(key_file))
+                    .filter(|key| key.exists())
+                    .collect(),
+            },
+        };
+        Self {
+            keys,
+            user,
+            user_from_env,
+            use_agent,
+            passphrase: match passphrase.is_empty() {
+                true => None,
+                false => Some(passphrase),
+            },
+            agent_attempted: Cell::new(false),
+            attempts: Cell::new(0),
+        }
+    }
+
+    /// Offer the SSH agent or the next key for the credentials callback. The agent is only offered
+    /// if SSH keys are allowed and an agent socket is available. If the agent has no usable
+    /// identity, libgit2 will ask for credentials again and we continue with the key files.
+    ///
+    /// The username from [`SSH_USER_ENV_VAR`] is preferred, followed by the username from the
+    /// remote URL and then the "User" entry from the SSH config (like the SSH client).
+    pub(crate) fn next(
+        &self,
+        username_from_url: Option<&str>,
+        allowed_types: CredentialType,
+    ) -> Result<Cred, git2::Error> {
+        let username = match (self.user_from_env, username_from_url) {
+            (false, Some(username)) => username,
+            _ => self.user.as_deref().ok_or_else(|| {
+                git2::Error::from_str("could not find username in remote url or ssh config")
+            })?,
+        };
+
+        // libgit2 asks for the username first if the remote URL does not contain one.
+        if allowed_types.contains(CredentialType::USERNAME)
+            && !allowed_types.contains(CredentialType::SSH_KEY)
+        {
+            return Cred::username(username);
+        }
+
+        if self.use_agent
+            && allowed_types.contains(CredentialType::SSH_KEY)
+            && !self.agent_attempted.get()
+            && ssh_agent_available()
+        {
+            // The agent is only marked as attempted if it was offered, so that it is not reported
+            // as the credentials that authenticated when it was unavailable.
+            self.agent_attempted.set(true);
+            debug!("trying ssh agent");
+            return Cred::ssh_key_from_agent(username);
+        }
+
+        let attempt = self.attempts.get();
+        let key = self
+            .keys
+            .get(attempt)
+            .ok_or_else(|| git2::Error::from_str("no ssh keys left to try"))?;
+        self.attempts.set(attempt + 1);
+        debug!("trying ssh key: {}", key.display());
+        Cred::ssh_key(username, None, key, self.passphrase)
+    }
+
+    /// Describe the credentials that were offered last, which are the credentials that
+    /// authenticated if the fetch succeeded.
+    pub(crate) fn last_offered(&self) -> Option<String> {
+        match self.attempts.get().checked_sub(1) {
+            Some(attempt) => self.keys.get(attempt).map(|key| key.display().to_string()),
+            None if self.agent_attempted.get() => Some("ssh agent".to_string()),
+            None => None,
+        }
+    }
+}
+
+/// Find the SSH key file provided via [`SSH_KEY_ENV_VAR`] (if set and not empty). An error is
+/// returned if the file does not exist rather than falling back to other credentials.
+pub fn ssh_key_override() -> anyhow::Result<Option<PathBuf>> {
+    let Some(key) = env::var_os(SSH_KEY_ENV_VAR).filter(|key| !key.is_empty()) else {
+        return Ok(None);
+    };
+    let key = PathBuf::from(key);
+    match key.is_file() {
+        true => Ok(Some(key)),
+        false => Err(anyhow::anyhow!(
+            "ssh key from ${SSH_KEY_ENV_VAR} not found: {}",
+            key.display()
+        )),
+    }
+}
+
+/// Find the host of an SSH remote URL, which is in either the "git@host:owner/repo" or the
+/// "ssh://git@host:port/owner/repo" format (the username is optional in both).
+pub(crate) fn ssh_host(url: &str) -> &str {
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = url.split('/').next().unwrap_or_default();
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    host.split(':').next().unwrap_or_default()
+}
+
+/// Check if an SSH agent can be reached. On Windows, the agent is reached via a named pipe, so we
+/// always try it.
+fn ssh_agent_available() -> bool {
+    cfg!(windows) || env::var_os("SSH_AUTH_SOCK").is_some()
+}
+
+/// Load the SSH config from the path in [`SSH_CONFIG_ENV_VAR`] or from the default path. If the
+/// file cannot be opened or parsed, the default [`SshConfig`] is used instead since the config is
+/// not required for fetching.
+fn ssh_config(home: &Path) -> SshConfig {
+    let path = match env::var_os(SSH_CONFIG_ENV_VAR) {
+        Some(path) => PathBuf::from(path),
+        None => home.join(".ssh").join("config"),
+    };
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            debug!(
+                "using default ssh config; could not open {} (ignored error: {e})",
+                path.display()
+            );
+            return SshConfig::default();
+        }
+    };
+    match SshConfig::default().parse(&mut BufReader::new(file), ParseRule::STRICT) {
+        Ok(config) => config,
+        Err(e) => {
+            debug!(
+                "using default ssh config; could not parse {} (ignored error: {e})",
+                path.display()
+            );
+            SshConfig::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn find_ssh_host() {
+        assert_eq!(ssh_host("git@github.com:owner/repo.git"), "github.com");
+        assert_eq!(ssh_host("github.com:owner/repo.git"), "github.com");
+        assert_eq!(
+            ssh_host("ssh://git@example.com:2222/owner/repo"),
+            "example.com"
+        );
+        assert_eq!(ssh_host("ssh://example.com/owner/repo"), "example.com");
+    }
+}
@@ -0,0 +1,67 @@
+//! This module contains [`ErrorLog`], which records the errors for individual repositories so that
+//! collection can continue past them.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use serde::{Deserialize, Serialize};
+
+/// The errors recorded for repositories that could not be opened, collected or fetched. Cloning
+/// the log shares it, so that fetches running on other threads record into the same log.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorLog {
+    errors: Arc<Mutex<Vec<RepositoryError>>>,
+}
+
+/// An error for a single repository.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepositoryError {
+    /// The path to the repository.
+    pub path: PathBuf,
+    /// The error message (including its causes).
+    pub message: String,
+}
+
+impl ErrorLog {
+    /// Record an error for the repository at the given path.
+    pub fn record(&self, path: &Path, message: String) {
+        self.errors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(RepositoryError {
+                path: path.to_path_buf(),
+                message,
+            });
+    }
+
+    /// Take the errors recorded so far, sorted by path (errors are recorded in parallel). The log
+    /// is cleared, so that each run (e.g. when watching) only reports its own errors.
+    pub fn take(&self) -> Vec<RepositoryError> {
+        let mut errors =
+            std::mem::take(&mut *self.errors.lock().unwrap_or_else(PoisonError::into_inner));
+        errors.sort_by(|a, b| a.path.cmp(&b.path));
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn shared_error_log() {
+        let log = ErrorLog::default();
+        let shared = log.clone();
+        shared.record(Path::new("b"), "could not fetch".to_string());
+        log.record(Path::new("a"), "could not open".to_string());
+        let errors = log.take();
+        let paths: Vec<&str> = errors
+            .iter()
+            .filter_map(|error| error.path.to_str())
+            .collect();
+        assert_eq!(paths, ["a", "b"]);
+        assert_eq!(errors[1].message, "could not fetch");
+        assert!(shared.take().is_empty());
+    }
+}
@@ -0,0 +1,89 @@
+//! This module contains [`FetchCache`], which tracks when remotes were last fetched across runs.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::cache_file::CacheFile;
+
+const CACHE_FILE_NAME: &str = "fetch_cache.json";
+
+/// An on-disk cache keyed by repository path and remote URL that records the last successful fetch
+/// time. It only decides whether or not a fetch is needed: status is always determined from the
+/// local state. All cache errors are logged and ignored, which means that we fall back to fetching.
+#[derive(Debug)]
+pub struct FetchCache {
+    file: CacheFile,
+    ttl: Duration,
+    entries: Mutex<BTreeMap<(PathBuf, String), u64>>,
+}
+
+/// The serialized form of a single cache entry.
+#[derive(Serialize, Deserialize)]
+struct FetchCacheEntry {
+    path: PathBuf,
+    url: String,
+    fetched_at: u64,
+}
+
+impl FetchCache {
+    /// Load the cache from `$XDG_CACHE_HOME/gfold`. Missing or unreadable caches are treated as
+    /// empty.
+    pub fn load(ttl: Duration) -> Self {
+        let file = CacheFile::new("fetch", CACHE_FILE_NAME);
+        let entries = file
+            .read::<FetchCacheEntry>()
+            .into_iter()
+            .map(|entry| ((entry.path, entry.url), entry.fetched_at))
+            .collect();
+        Self {
+            file,
+            ttl,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// Check if the remote was successfully fetched for the repository within the TTL.
+    pub fn is_fresh(&self, repo_path: &Path, url: &str) -> bool {
+        let Ok(entries) = self.entries.lock() else {
+            return false;
+        };
+        entries
+            .get(&(repo_path.to_path_buf(), url.to_string()))
+            .is_some_and(|fetched_at| now().saturating_sub(*fetched_at) < self.ttl.as_secs())
+    }
+
+    /// Record a successful fetch of the remote for the repository.
+    pub fn record(&self, repo_path: &Path, url: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert((repo_path.to_path_buf(), url.to_string()), now());
+        }
+    }
+
+    /// Write the cache back to disk.
+    pub fn save(&self) {
+        let Ok(entries) = self.entries.lock() else {
+            return;
+        };
+        let entries: Vec<FetchCacheEntry> = entries
+            .iter()
+            .map(|((path, url), fetched_at)| FetchCacheEntry {
+                path: path.clone(),
+                url: url.clone(),
+                fetched_at: *fetched_at,
+            })
+            .collect();
+        self.file.write(&entries);
+    }
+}
+
+/// The current time in seconds since the Unix epoch (zero if the clock is before the epoch).
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,126 @@
+//! This module contains [`FetchLimiter`], which bounds the number of remote fetches that run at
+//! the same time.
+
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// A counting semaphore for remote fetches. Collection runs on the [`rayon`] thread pool, which is
+/// sized for local work, so this allows the (IO-bound) fetches to be limited separately (e.g. to
+/// avoid being rate limited by a host). See the README for when running more fetches at the same
+/// time pays off (and `just bench-fetch` for comparing it against sequential fetches).
+#[derive(Debug)]
+pub struct FetchLimiter {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+/// A permit to run a fetch, which is returned to its [`FetchLimiter`] when dropped.
+#[derive(Debug)]
+pub struct FetchPermit {
+    limiter: Arc<FetchLimiter>,
+}
+
+impl FetchLimiter {
+    /// Create a limiter that allows up to the given number of fetches at the same time (at least
+    /// one).
+    pub fn new(limit: usize) -> Arc<Self> {
+        Arc::new(Self {
+            available: Mutex::new(limit.max(1)),
+            released: Condvar::new(),
+        })
+    }
+
+    /// Block until a fetch is allowed to run. The fetch counts against the limit until the permit
+    /// is dropped.
+    pub fn acquire(self: &Arc<Self>) -> FetchPermit {
+        let mut available = self
+            .available
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        while *available == 0 {
+            available = self
+                .released
+                .wait(available)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        *available -= 1;
+        FetchPermit {
+            limiter: Arc::clone(self),
+        }
+    }
+
+    /// Block until a fetch is allowed to run like [`Self::acquire()`], but for no longer than the
+    /// timeout. Returns `None` if no permit was returned in time.
+    pub fn acquire_timeout(self: &Arc<Self>, timeout: Duration) -> Option<FetchPermit> {
+        let deadline = Instant::now() + timeout;
+        let mut available = self
+            .available
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        while *available == 0 {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return None;
+            }
+            available = self
+                .released
+                .wait_timeout(available, remaining)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+        *available -= 1;
+        Some(FetchPermit {
+            limiter: Arc::clone(self),
+        })
+    }
+}
+
+impl Drop for FetchPermit {
+    fn drop(&mut self) {
+        let mut available = self
+            .limiter
+            .available
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *available += 1;
+        self.limiter.released.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn limit_concurrent_fetches() {
+        let limiter = FetchLimiter::new(2);
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let _permit = limiter.acquire();
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(10));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn acquire_with_timeout() {
+        let limiter = FetchLimiter::new(1);
+        let permit = limiter.acquire_timeout(Duration::from_millis(10));
+        assert!(permit.is_some());
+        assert!(limiter.acquire_timeout(Duration::from_millis(10)).is_none());
+        drop(permit);
+        assert!(limiter.acquire_timeout(Duration::from_millis(10)).is_some());
+    }
+}
@@ -0,0 +1,138 @@
+//! This module contains a fallback that uses the `git` binary for repositories that cannot be
+//! opened with `libgit2` (e.g. repositories using the "worktreeconfig" extension).
+
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{Result, anyhow};
+use log::debug;
+
+use crate::status::Status;
+
+/// The details recovered from `git status` for a repository.
+#[derive(Debug)]
+pub(super) struct GitShellStatus {
+    pub(super) status: Status,
+    pub(super) branch: String,
+    pub(super) ahead: usize,
+    pub(super) behind: usize,
+}
+
+impl GitShellStatus {
+    /// Run `git status` with stable, machine-readable output for the repository and interpret the
+    /// results. Unlike [`Status::find()`], ahead and behind counts are relative to the upstream
+    /// tracking branch and a missing upstream is considered "clean".
+    pub(super) fn find(repo_path: &Path) -> Result<Self> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(repo_path)
+            .args([
+                "status",
+                "--porcelain=v2",
+                "--branch",
+                "--untracked-files=all",
+            ])
+            .output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "git status failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        debug!("recovered status with git binary: {}", repo_path.display());
+        Self::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    fn parse(output: &str) -> Result<Self> {
+        let (mut head, mut oid) = (None, None);
+        let (mut ahead, mut behind) = (0, 0);
+        let (mut unclean, mut conflicted) = (false, false);
+        for line in output.lines() {
+            if let Some(value) = line.strip_prefix("# branch.head ") {
+                head = Some(value);
+            } else if let Some(value) = line.strip_prefix("# branch.oid ") {
+                oid = Some(value);
+            } else if let Some(value) = line.strip_prefix("# branch.ab ") {
+                // The format is "+<ahead> -<behind>".
+                for count in value.split_whitespace() {
+                    if let Some(count) = count.strip_prefix('+') {
+                        ahead = count.parse()?;
+                    } else if let Some(count) = count.strip_prefix('-') {
+                        behind = count.parse()?;
+                    }
+                }
+            } else if line.starts_with("u ") {
+                // Unmerged entries are reported with their own prefix.
+                conflicted = true;
+            } else if !line.starts_with('#') {
+                unclean = true;
+            }
+        }
+
+        // Mirror how branches are displayed for repositories opened with "libgit2".
+        let branch = match (head, oid) {
+            (Some("(detached)"), Some(oid)) => {
+                format!("detached@{}", oid.get(..7).unwrap_or(oid))
+            }
+            (Some(head), _) => head.to_string(),
+            (None, _) => return Err(anyhow!("could not find branch in git status output")),
+        };
+        let status = if conflicted {
+            Status::Conflicted
+        } else if unclean {
+            Status::Unclean
+        } else if behind > 0 && ahead > 0 {
+            Status::Diverged
+        } else if behind > 0 {
+            Status::Unpulled
+        } else if ahead > 0 {
+            Status::Unpushed
+        } else {
+            Status::Clean
+        };
+        Ok(Self {
+            status,
+            branch,
+            ahead,
+            behind,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[allow(clippy::panic_in_result_fn)]
+    #[test]
+    fn parse_porcelain_v2() -> Result<()> {
+        let unpushed = GitShellStatus::parse(
+            "# branch.oid 0123456789abcdef\n# branch.head main\n# branch.upstream origin/main\n# branch.ab +2 -0\n",
+        )?;
+        assert_eq!(
+            (
+                unpushed.status,
+                unpushed.branch.as_str(),
+                unpushed.ahead,
+                unpushed.behind
+            ),
+            (Status::Unpushed, "main", 2, 0)
+        );
+
+        let detached = GitShellStatus::parse(
+            "# branch.oid 0123456789abcdef\n# branch.head (detached)\n? new\n",
+        )?;
+        assert_eq!(
+            (detached.status, detached.branch.as_str()),
+            (Status::Unclean, "detached@0123456")
+        );
+
+        let conflicted = GitShellStatus::parse(
+            "# branch.oid 0123456789abcdef\n# branch.head main\nu UU N... 100644 100644 100644 100644 a b c f\n",
+        )?;
+        assert_eq!(conflicted.status, Status::Conflicted);
+        Ok(())
+    }
+}
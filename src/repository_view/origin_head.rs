@@ -0,0 +1,89 @@
+//! This module contains [`OriginHeadLog`], which records the repositories whose "origin/HEAD" was
+//! refreshed from the remote (e.g. after the default branch was renamed from "master" to "main").
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::{Context, Result, anyhow};
+use git2::{Remote, Repository};
+use log::debug;
+
+/// The name of the remote whose HEAD is refreshed.
+pub(crate) const ORIGIN: &str = "origin";
+const ORIGIN_HEAD: &str = "refs/remotes/origin/HEAD";
+const ORIGIN_PREFIX: &str = "refs/remotes/origin/";
+
+/// The changes to "origin/HEAD" made while fetching. Cloning the log shares it, so that fetches
+/// running on other threads record into the same log.
+#[derive(Debug, Clone, Default)]
+pub struct OriginHeadLog {
+    changes: Arc<Mutex<Vec<OriginHeadChange>>>,
+}
+
+/// A change to "origin/HEAD" for a single repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginHeadChange {
+    /// The path to the repository.
+    pub path: PathBuf,
+    /// The default branch that "origin/HEAD" pointed to before (`None` if it was not set).
+    pub previous: Option<String>,
+    /// The default branch that "origin/HEAD" points to now.
+    pub current: String,
+}
+
+impl OriginHeadLog {
+    /// Update "origin/HEAD" to point to the default branch advertised by the remote, which must
+    /// have just been fetched (i.e. it is still connected or its advertised references are still
+    /// available). The change is recorded for the given path if "origin/HEAD" was updated. An
+    /// error is returned if the advertised default branch has not been fetched or if the
+    /// reference cannot be written (e.g. due to a permission error).
+    pub(crate) fn refresh(
+        &self,
+        path: &Path,
+        repo: &Repository,
+        remote: &Remote<'_>,
+    ) -> Result<()> {
+        let advertised = remote.default_branch()?;
+        let current = advertised
+            .as_str()
+            .and_then(|name| name.strip_prefix("refs/heads/"))
+            .ok_or_else(|| anyhow!("remote HEAD is not a branch or is invalid UTF-8"))?
+            .to_string();
+        let previous = repo.find_reference(ORIGIN_HEAD).ok().and_then(|reference| {
+            reference
+                .symbolic_target()
+                .and_then(|target| target.strip_prefix(ORIGIN_PREFIX))
+                .map(str::to_string)
+        });
+        if previous.as_deref() == Some(current.as_str()) {
+            debug!("origin/HEAD is up to date: {current}");
+            return Ok(());
+        }
+
+        let target = format!("{ORIGIN_PREFIX}{current}");
+        repo.find_reference(&target).with_context(|| {
+            format!("could not refresh origin/HEAD; default branch has not been fetched: {current}")
+        })?;
+        repo.reference_symbolic(ORIGIN_HEAD, &target, true, "gfold: refresh origin/HEAD")
+            .context("could not refresh origin/HEAD")?;
+        debug!("refreshed origin/HEAD from {previous:?} to {current}");
+        self.changes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(OriginHeadChange {
+                path: path.to_path_buf(),
+                previous,
+                current,
+            });
+        Ok(())
+    }
+
+    /// Take the changes recorded so far, sorted by path. The log is cleared, so that each run (e.g.
+    /// when watching) only reports its own changes.
+    pub fn take(&self) -> Vec<OriginHeadChange> {
+        let mut changes =
+            std::mem::take(&mut *self.changes.lock().unwrap_or_else(PoisonError::into_inner));
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        changes
+    }
+}
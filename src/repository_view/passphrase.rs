@@ -0,0 +1,99 @@
+//! This module contains [`PassphrasePrompt`], which asks for SSH key passphrases interactively.
+
+use std::io::{self, BufRead, Write};
+use std::sync::{Arc, Mutex};
+
+use log::debug;
+
+/// Prompts for the SSH key passphrase on the terminal after an authentication failure. The
+/// passphrase is only asked for once and is cached for the rest of the run (clones share the
+/// cache), so that repositories do not each prompt again.
+#[derive(Debug, Clone, Default)]
+pub struct PassphrasePrompt {
+    cached: Arc<Mutex<Option<String>>>,
+}
+
+impl PassphrasePrompt {
+    /// Find a passphrase to retry with after the given one failed. Returns `None` if there is
+    /// nothing new to try (i.e. the cached passphrase failed too or we could not prompt).
+    pub(super) fn next(&self, failed: &str) -> Option<String> {
+        // The lock is held while prompting, which ensures that concurrent fetches wait for the
+        // first prompt rather than prompting again.
+        let mut cached = self.cached.lock().ok()?;
+        if let Some(passphrase) = cached.as_ref() {
+            return (passphrase != failed).then(|| passphrase.clone());
+        }
+        let passphrase = match read_hidden("Enter passphrase for SSH key: ") {
+            Ok(passphrase) => passphrase,
+            Err(e) => {
+                debug!("could not prompt for passphrase (ignored error: {e})");
+                String::new()
+            }
+        };
+        *cached = Some(passphrase.clone());
+        (passphrase != failed).then_some(passphrase)
+    }
+}
+
+/// Write the prompt to `stderr` and read a line from `stdin` without echoing it.
+fn read_hidden(prompt: &str) -> io::Result<String> {
+    let mut stderr = io::stderr().lock();
+    write!(stderr, "{prompt}")?;
+    stderr.flush()?;
+
+    let echo = EchoGuard::disable()?;
+    let mut line = String::new();
+    let result = io::stdin().lock().read_line(&mut line);
+    drop(echo);
+    writeln!(stderr)?;
+    result?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Disables echoing on the terminal attached to `stdin` until dropped.
+#[cfg(unix)]
+struct EchoGuard {
+    original: libc::termios,
+}
+
+#[cfg(unix)]
+impl EchoGuard {
+    fn disable() -> io::Result<Self> {
+        // SAFETY: "termios" is plain data that "tcgetattr" fully initializes on success.
+        let mut original = unsafe { std::mem::zeroed::<libc::termios>() };
+        // SAFETY: the pointer is valid for the duration of the call.
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut hidden = original;
+        hidden.c_lflag &= !libc::ECHO;
+        // SAFETY: the pointer is valid for the duration of the call.
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &hidden) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { original })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for EchoGuard {
+    fn drop(&mut self) {
+        // SAFETY: the pointer is valid for the duration of the call.
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original) };
+    }
+}
+
+/// Echo cannot be disabled on this platform, so we refuse to prompt rather than display the
+/// passphrase.
+#[cfg(not(unix))]
+struct EchoGuard;
+
+#[cfg(not(unix))]
+impl EchoGuard {
+    fn disable() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "hidden input is not supported on this platform",
+        ))
+    }
+}
@@ -0,0 +1,76 @@
+//! This module contains [`Profile`], which records how long each repository took to collect.
+
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// Wall-clock timings for every repository collected during a run, which are used to find the
+/// slowest repositories (e.g. one with a hanging fetch).
+#[derive(Debug, Default)]
+pub struct Profile {
+    timings: Mutex<Vec<Timing>>,
+}
+
+/// How long it took to collect the view for a single repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timing {
+    /// The path to the repository.
+    pub path: PathBuf,
+    /// The time spent on the entire repository, including the fetch.
+    pub total: Duration,
+    /// The time spent fetching from the remote (zero if it was not fetched).
+    pub fetch: Duration,
+}
+
+impl Timing {
+    /// The time spent on the repository itself (i.e. everything except for the fetch).
+    pub fn local(&self) -> Duration {
+        self.total.saturating_sub(self.fetch)
+    }
+}
+
+impl Profile {
+    /// Record the timing for a repository.
+    pub fn record(&self, timing: Timing) {
+        self.timings
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(timing);
+    }
+
+    /// Take the slowest timings recorded so far (slowest first), up to the given count. All
+    /// timings are cleared, so that each run (e.g. when watching) is profiled on its own.
+    pub fn take_slowest(&self, count: usize) -> Vec<Timing> {
+        let mut timings =
+            std::mem::take(&mut *self.timings.lock().unwrap_or_else(PoisonError::into_inner));
+        timings.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.path.cmp(&b.path)));
+        timings.truncate(count);
+        timings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn slowest_timings() {
+        let profile = Profile::default();
+        for (name, millis) in [("a", 30), ("b", 10), ("c", 20)] {
+            profile.record(Timing {
+                path: PathBuf::from(name),
+                total: Duration::from_millis(millis),
+                fetch: Duration::from_millis(5),
+            });
+        }
+        let slowest = profile.take_slowest(2);
+        let paths: Vec<&str> = slowest
+            .iter()
+            .filter_map(|timing| timing.path.to_str())
+            .collect();
+        assert_eq!(paths, ["a", "c"]);
+        assert_eq!(slowest[0].local(), Duration::from_millis(25));
+        assert!(profile.take_slowest(2).is_empty());
+    }
+}
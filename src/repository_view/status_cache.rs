@@ -0,0 +1,229 @@
+//! This module contains [`StatusCache`], which reuses the [`Status`] of unchanged repositories
+//! across runs.
+
+use std::collections::BTreeMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use anyhow::Result;
+use git2::{Reference, Remote, Repository};
+use log::{debug, trace};
+use serde::{Deserialize, Serialize};
+
+use super::cache_file::CacheFile;
+use crate::status::Status;
+
+const CACHE_FILE_NAME: &str = "status_cache.json";
+
+/// An on-disk cache keyed by repository path that records the [`Status`] of each repository along
+/// with a fingerprint of its state. The fingerprint covers where HEAD points, the modification
+/// time and size of the index and the remote-tracking branches for the current branch, so any
+/// change to HEAD, the index or the remote-tracking branches invalidates the cached status.
+/// Changes to the working tree that do not touch the index (e.g. unstaged edits) are not detected.
+/// All cache errors are logged and ignored, which means that we fall back to finding the status.
+#[derive(Debug)]
+pub struct StatusCache {
+    file: CacheFile,
+    entries: Mutex<BTreeMap<PathBuf, (u64, Status)>>,
+}
+
+/// The serialized form of a single cache entry.
+#[derive(Serialize, Deserialize)]
+struct StatusCacheEntry {
+    path: PathBuf,
+    fingerprint: u64,
+    status: Status,
+}
+
+impl StatusCache {
+    /// Load the cache from `$XDG_CACHE_HOME/gfold`. Missing or unreadable caches are treated as
+    /// empty.
+    pub fn load() -> Self {
+        let file = CacheFile::new("status", CACHE_FILE_NAME);
+        let entries = file
+            .read::<StatusCacheEntry>()
+            .into_iter()
+            .map(|entry| (entry.path, (entry.fingerprint, entry.status)))
+            .collect();
+        Self {
+            file,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// Find the [`Status`] for the repository like [`Status::find()`], but reuse the cached status
+    /// if the repository has not changed since it was cached. Repositories that cannot be
+    /// fingerprinted are never cached.
+    pub fn find<'a>(
+        &self,
+        repo_path: &Path,
+        repo: &'a Repository,
+        remote_preference: &[String],
+    ) -> Result<(Status, Option<Reference<'a>>, Option<Remote<'a>>)> {
+        let head = Status::find_head(repo)?;
+        let Some(fingerprint) = fingerprint(repo, head.as_ref(), remote_preference) else {
+            return Status::find(repo, remote_preference);
+        };
+        let cached = self.entries.lock().ok().and_then(|entries| {
+            entries
+                .get(repo_path)
+                .filter(|(cached_fingerprint, _)| *cached_fingerprint == fingerprint)
+                .map(|(_, status)| *status)
+        });
+        if let Some(status) = cached {
+            debug!(
+                "reusing cached status ({}): {}",
+                status.as_str(),
+                repo_path.display()
+            );
+            let (remote, _) = Status::find_remote(repo, remote_preference)?;
+            return Ok((status, head, remote));
+        }
+
+        let (status, head, remote) = Status::find(repo, remote_preference)?;
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(repo_path.to_path_buf(), (fingerprint, status));
+        }
+        Ok((status, head, remote))
+    }
+
+    /// Write the cache back to disk.
+    pub fn save(&self) {
+        let Ok(entries) = self.entries.lock() else {
+            return;
+        };
+        let entries: Vec<StatusCacheEntry> = entries
+            .iter()
+            .map(|(path, (fingerprint, status))| StatusCacheEntry {
+                path: path.clone(),
+                fingerprint: *fingerprint,
+                status: *status,
+            })
+            .collect();
+        self.file.write(&entries);
+    }
+}
+
+/// Hash where HEAD points, the modification time and size of the index (if any), the targets of
+/// the remote-tracking branches with the same name as the current branch and the remote preference
+/// (which decides the remote that the status is relative to). The value will be `None` if any of
+/// them cannot be read.
+fn fingerprint(
+    repo: &Repository,
+    head: Option<&Reference<'_>>,
+    remote_preference: &[String],
+) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    remote_preference.hash(&mut hasher);
+    head.and_then(Reference::name).hash(&mut hasher);
+    head.and_then(Reference::target)
+        .map(|oid| oid.to_string())
+        .hash(&mut hasher);
+
+    let index = repo.path().join("index");
+    match fs::metadata(&index) {
+        Ok(metadata) => {
+            let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+            (modified.as_nanos(), metadata.len()).hash(&mut hasher);
+        }
+        // Bare and empty repositories do not have an index.
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None::<u128>.hash(&mut hasher),
+        Err(e) => {
+            trace!("ignored error: {e}");
+            return None;
+        }
+    }
+
+    if let Some(shorthand) = head
+        .filter(|head| head.is_branch())
+        .and_then(Reference::shorthand)
+    {
+        let remotes = match repo.remotes() {
+            Ok(remotes) => remotes,
+            Err(e) => {
+                trace!("ignored error: {e}");
+                return None;
+            }
+        };
+        for remote in remotes.iter().flatten() {
+            let target = repo
+                .find_reference(&format!("refs/remotes/{remote}/{shorthand}"))
+                .ok()
+                .and_then(|reference| reference.target())
+                .map(|oid| oid.to_string());
+            (remote, target).hash(&mut hasher);
+        }
+    }
+    Some(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Oid, Signature};
+    use pretty_assertions::assert_ne;
+    use tempfile::tempdir;
+
+    #[allow(clippy::panic_in_result_fn)]
+    #[test]
+    fn fingerprint_changes() -> Result<()> {
+        let root = tempdir()?;
+        let repo = Repository::init(root.path())?;
+        let signature = Signature::now("neloth", "neloth@tel-mora.net")?;
+        let commit = |message: &str| -> Result<Oid> {
+            let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+            let parents = match Status::find_head(&repo)? {
+                Some(head) => vec![head.peel_to_commit()?],
+                None => Vec::new(),
+            };
+            let parents: Vec<_> = parents.iter().collect();
+            Ok(repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )?)
+        };
+        let stage = |name: &str| -> Result<()> {
+            fs::write(root.path().join(name), name)?;
+            let mut index = repo.index()?;
+            index.add_path(Path::new(name))?;
+            Ok(index.write()?)
+        };
+        let current = || {
+            let head = Status::find_head(&repo).ok().flatten();
+            fingerprint(&repo, head.as_ref(), &[])
+        };
+
+        stage("first")?;
+        let first = commit("first")?;
+        repo.remote("origin", "https://github.com/nickgerace/gfold.git")?;
+        let committed = current();
+
+        stage("second")?;
+        let staged = current();
+        assert_ne!(committed, staged);
+
+        let second = commit("second")?;
+        let recommitted = current();
+        assert_ne!(staged, recommitted);
+
+        let branch = Status::find_head(&repo)?
+            .and_then(|head| head.shorthand().map(str::to_string))
+            .unwrap_or_default();
+        let remote_branch = format!("refs/remotes/origin/{branch}");
+        repo.reference(&remote_branch, first, true, "test")?;
+        let tracked = current();
+        assert_ne!(recommitted, tracked);
+
+        repo.reference(&remote_branch, second, true, "test")?;
+        assert_ne!(tracked, current());
+        Ok(())
+    }
+}
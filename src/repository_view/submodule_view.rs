@@ -1,8 +1,8 @@
 //! This module contains the ability to gather information on submodules for a given [`Repository`].
 
 use anyhow::{Result, anyhow};
-use git2::Repository;
-use log::error;
+use git2::{Repository, SubmoduleIgnore, SubmoduleStatus};
+use log::{debug, error};
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -12,24 +12,46 @@ use crate::status::Status;
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct SubmoduleView {
     pub name: String,
+    /// The [`Status`] of the submodule, which is "unknown" if it is not initialized.
     pub status: Status,
+    /// Whether or not the submodule is initialized (i.e. it has been cloned into the working tree).
+    pub initialized: bool,
+    /// Whether or not the commit checked out in the submodule differs from the commit recorded in
+    /// the index of the superproject (i.e. "git submodule update" is needed). The value will be
+    /// `false` if the submodule is not initialized.
+    pub out_of_sync: bool,
 }
 
 impl SubmoduleView {
-    /// Generate a list of [`submodule view(s)`](Self) for a given [`Repository`].
-    pub fn list(repo: &Repository) -> Result<Vec<Self>> {
+    /// Generate a list of [`submodule view(s)`](Self) for a given [`Repository`]. Statuses are
+    /// relative to the first remote found in the order of preference.
+    pub fn list(repo: &Repository, remote_preference: &[String]) -> Result<Vec<Self>> {
         let mut submodules = Vec::new();
         for submodule in repo.submodules()? {
+            let name = submodule
+                .name()
+                .ok_or(anyhow!("submodule name is invalid UTF-8"))?;
+            if repo
+                .submodule_status(name, SubmoduleIgnore::Unspecified)?
+                .contains(SubmoduleStatus::WD_UNINITIALIZED)
+            {
+                debug!("submodule is not initialized: {name}");
+                submodules.push(Self {
+                    name: name.to_string(),
+                    status: Status::Unknown,
+                    initialized: false,
+                    out_of_sync: false,
+                });
+                continue;
+            }
             match submodule.open() {
                 Ok(subrepo) => {
-                    let (status, _, _) = Status::find(&subrepo)?;
-                    let name = submodule
-                        .name()
-                        .ok_or(anyhow!("submodule name is invalid UTF-8"))?;
-
+                    let (status, _, _) = Status::find(&subrepo, remote_preference)?;
                     submodules.push(Self {
                         name: name.to_string(),
                         status,
+                        initialized: true,
+                        out_of_sync: submodule.workdir_id() != submodule.index_id(),
                     });
                 }
                 Err(e) => error!("could not open submodule as repository: {e}"),
@@ -1,18 +1,30 @@
 //! This module contains the [`crate::status::Status`] type.
 
+use std::cmp::Ordering;
+
 use anyhow::Result;
+use clap::ValueEnum;
 use git2::{BranchType, ErrorCode, Reference, Remote, Repository, StatusOptions};
 use log::debug;
 use serde::{Deserialize, Serialize};
 
-/// A summarized interpretation of the status of a Git working tree.
+/// The remote that is preferred if there is no preference.
+const DEFAULT_REMOTE: &str = "origin";
+
+/// A summarized interpretation of the status of a Git working tree. Statuses are ordered by their
+/// [`severity`](Status::severity).
 #[remain::sorted]
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, ValueEnum)]
 pub enum Status {
     /// Corresponds to a "bare" working tree.
     Bare,
     /// Corresponds to a "clean" working tree.
     Clean,
+    /// Corresponds to a working tree with conflicted entries (e.g. from an unfinished merge).
+    Conflicted,
+    /// When the current branch and the remote both have commits that the other does not (i.e. a
+    /// rebase or merge is needed rather than a fast-forward).
+    Diverged,
     /// Corresponds to an "unclean" working tree.
     Unclean,
     /// Provided if the state of the working tree could neither be found nor determined.
@@ -23,12 +35,26 @@ pub enum Status {
     Unpushed,
 }
 
+impl Ord for Status {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.severity().cmp(&other.severity())
+    }
+}
+
+impl PartialOrd for Status {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Status {
     /// Converts the enum into a borrowed, static `str`.
     pub fn as_str(&self) -> &'static str {
         match self {
             Self::Bare => "bare",
             Self::Clean => "clean",
+            Self::Conflicted => "conflicted",
+            Self::Diverged => "diverged",
             Self::Unclean => "unclean",
             Self::Unknown => "unknown",
             Self::Unpulled => "unpulled",
@@ -36,25 +62,36 @@ impl Status {
         }
     }
 
-    /// Find the [`Status`] for a given [`Repository`]. The
-    /// [`head`](Option<git2::Reference>) and [`remote`](Option<git2::Remote>) are also returned.
-    pub fn find(repo: &Repository) -> Result<(Status, Option<Reference<'_>>, Option<Remote<'_>>)> {
-        let head = match repo.head() {
-            Ok(head) => Some(head),
-            Err(ref e)
-                if e.code() == ErrorCode::UnbornBranch || e.code() == ErrorCode::NotFound =>
-            {
-                None
-            }
-            Err(e) => return Err(e.into()),
-        };
+    /// Ranks the status from least to most in need of attention (starting at zero for "clean"). The
+    /// ranking is stable (i.e. scripts can rely on it): clean < unclean < unpushed < unpulled <
+    /// diverged < conflicted < bare < unknown.
+    pub fn severity(&self) -> u8 {
+        match self {
+            Self::Clean => 0,
+            Self::Unclean => 1,
+            Self::Unpushed => 2,
+            Self::Unpulled => 3,
+            Self::Diverged => 4,
+            Self::Conflicted => 5,
+            Self::Bare => 6,
+            Self::Unknown => 7,
+        }
+    }
 
-        // Greedily chooses a remote if "origin" is not found.
-        let (remote, remote_name) = match repo.find_remote("origin") {
-            Ok(origin) => (Some(origin), Some("origin".to_string())),
-            Err(e) if e.code() == ErrorCode::NotFound => Self::choose_remote_greedily(repo)?,
-            Err(e) => return Err(e.into()),
-        };
+    /// Check if the status is at least as severe as the given status.
+    pub fn is_at_least(&self, status: Status) -> bool {
+        *self >= status
+    }
+
+    /// Find the [`Status`] for a given [`Repository`] relative to the first remote found in the
+    /// order of preference (see [`Self::find_remote()`]). The
+    /// [`head`](Option<git2::Reference>) and [`remote`](Option<git2::Remote>) are also returned.
+    pub fn find<'a>(
+        repo: &'a Repository,
+        remote_preference: &[String],
+    ) -> Result<(Status, Option<Reference<'a>>, Option<Remote<'a>>)> {
+        let head = Self::find_head(repo)?;
+        let (remote, remote_name) = Self::find_remote(repo, remote_preference)?;
 
         // We'll include all untracked files and directories in the status options.
         let mut opts = StatusOptions::new();
@@ -63,23 +100,14 @@ impl Status {
         // If "head" is "None" and statuses are empty, then the repository_view must be clean because there
         // are no commits to push.
         let status = match repo.statuses(Some(&mut opts)) {
-            Ok(v) if v.is_empty() => match &head {
-                Some(head) => match remote_name {
-                    Some(remote_name) => match Self::is_unpushed(repo, head, &remote_name)? {
-                        true => match Self::has_unmerged_changes(repo, head, &remote_name)? {
-                            true => Status::Unpulled,
-                            false => Status::Unpushed,
-                        },
-                        false => match Self::has_unmerged_changes(repo, head, &remote_name)? {
-                            true => Status::Unpulled,
-                            false => Status::Clean,
-                        },
-                    },
-                    None => Status::Clean,
-                },
-                None => Status::Clean,
+            Ok(v) if v.is_empty() => {
+                Self::find_relative_to_remote(repo, head.as_ref(), remote_name)?
+            }
+            // Only entries with conflicts count, so staged changes are still "unclean".
+            Ok(_) => match repo.index()?.has_conflicts() {
+                true => Status::Conflicted,
+                false => Status::Unclean,
             },
-            Ok(_) => Status::Unclean,
             Err(e) if e.code() == ErrorCode::BareRepo => Status::Bare,
             Err(e) => return Err(e.into()),
         };
@@ -87,6 +115,82 @@ impl Status {
         Ok((status, head, remote))
     }
 
+    /// Find the status again after the remote-tracking references of a [`Repository`] have changed
+    /// (e.g. after fetching). Only statuses for clean working trees depend on the remote, so all
+    /// other statuses are returned unchanged without finding the status of the working tree again.
+    pub fn refresh(
+        self,
+        repo: &Repository,
+        head: Option<&Reference<'_>>,
+        remote_preference: &[String],
+    ) -> Result<Status> {
+        match self {
+            Status::Clean | Status::Diverged | Status::Unpulled | Status::Unpushed => {
+                let (_, remote_name) = Self::find_remote(repo, remote_preference)?;
+                Self::find_relative_to_remote(repo, head, remote_name)
+            }
+            status => Ok(status),
+        }
+    }
+
+    // Finds the status of a clean working tree relative to the remote.
+    fn find_relative_to_remote(
+        repo: &Repository,
+        head: Option<&Reference<'_>>,
+        remote_name: Option<String>,
+    ) -> Result<Status> {
+        Ok(match head {
+            Some(head) => match remote_name {
+                Some(remote_name) => match Self::is_unpushed(repo, head, &remote_name)? {
+                    true => match Self::has_unmerged_changes(repo, head, &remote_name)? {
+                        true => Status::Diverged,
+                        false => Status::Unpushed,
+                    },
+                    false => match Self::has_unmerged_changes(repo, head, &remote_name)? {
+                        true => Status::Unpulled,
+                        false => Status::Clean,
+                    },
+                },
+                None => Status::Clean,
+            },
+            None => Status::Clean,
+        })
+    }
+
+    /// Find the HEAD of the [`Repository`]. The value will be `None` if HEAD is unborn (e.g. the
+    /// repository is empty).
+    pub fn find_head(repo: &Repository) -> Result<Option<Reference<'_>>> {
+        match repo.head() {
+            Ok(head) => Ok(Some(head)),
+            Err(ref e)
+                if e.code() == ErrorCode::UnbornBranch || e.code() == ErrorCode::NotFound =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Find the first remote in the order of preference (or "origin" if there is no preference)
+    /// and its name. A remote is greedily chosen if none of the preferred remotes are found.
+    pub fn find_remote<'a>(
+        repo: &'a Repository,
+        remote_preference: &[String],
+    ) -> Result<(Option<Remote<'a>>, Option<String>)> {
+        let preferred = match remote_preference.is_empty() {
+            true => vec![DEFAULT_REMOTE],
+            false => remote_preference.iter().map(String::as_str).collect(),
+        };
+        for name in preferred {
+            match repo.find_remote(name) {
+                Ok(remote) => return Ok((Some(remote), Some(name.to_string()))),
+                Err(e) if matches!(e.code(), ErrorCode::NotFound | ErrorCode::InvalidSpec) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(Self::choose_remote_greedily(repo)?)
+    }
+
     // Checks if local commit(s) on the current branch have not yet been pushed to the remote.
     fn is_unpushed(
         repo: &Repository,
@@ -125,9 +229,12 @@ impl Status {
         remote_name: &str,
     ) -> Result<bool, git2::Error> {
         let local_head = head.peel_to_commit()?;
-        let short_remote_branch_name = head.shorthand().unwrap();
+        let Some(short_remote_branch_name) = head.shorthand() else {
+            debug!("assuming merged; could not determine shorthand for head");
+            return Ok(false);
+        };
         let remote_branch_name = format!("{}/{}", remote_name, short_remote_branch_name);
-        
+
         // Find the remote branch.
         let remote_branch = match repo.find_branch(&remote_branch_name, BranchType::Remote) {
             Ok(branch) => branch.into_reference(),
@@ -140,7 +247,7 @@ impl Status {
         Ok(changes.0 > 0)
     }
 
-    /// Greedily chooses a remote if "origin" is not found.
+    /// Greedily chooses a remote if none of the preferred remotes are found.
     pub fn choose_remote_greedily(
         repository: &Repository,
     ) -> Result<(Option<Remote<'_>>, Option<String>), git2::Error> {
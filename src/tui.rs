@@ -0,0 +1,457 @@
+//! This module contains the interactive terminal UI for browsing collected repositories.
+
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use gfld::display::color::StatusPalette;
+use gfld::repository_view::RepositoryView;
+use gfld::status::Status;
+use log::debug;
+use termcolor::{Ansi, Color, ColorSpec, WriteColor};
+
+/// Switches to the alternate screen and hides the cursor.
+const ENTER_ALTERNATE_SCREEN: &str = "\x1b[?1049h\x1b[?25l";
+/// Resets colors, shows the cursor and switches back to the original screen.
+const LEAVE_ALTERNATE_SCREEN: &str = "\x1b[0m\x1b[?25h\x1b[?1049l";
+/// Clears the screen and moves the cursor to the top-left corner.
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+/// The key bindings, which are displayed at the bottom of the screen.
+const KEY_BINDINGS: &str =
+    "type to search | tab: filter status | enter: open shell | ctrl-y: copy url | esc: quit";
+/// The size of the terminal if it cannot be found, in rows and columns.
+const DEFAULT_SIZE: (usize, usize) = (24, 80);
+
+/// Check if the interactive list can be used (i.e. both `stdin` and `stdout` are attached to a
+/// terminal on a platform where raw input is supported).
+pub(crate) fn is_supported() -> bool {
+    cfg!(unix) && io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// Display the views in a scrollable list on the alternate screen until the user quits. Typing
+/// searches by name, tab cycles through the statuses to filter by, enter opens a shell (from
+/// `$SHELL`) in the selected repository and ctrl-y copies its URL to the clipboard (with the OSC 52
+/// escape sequence, which the terminal must support). The original screen is restored afterwards.
+pub(crate) fn run(views: Vec<RepositoryView>, palette: StatusPalette, colored: bool) -> Result<()> {
+    let mut browser = Browser::new(views, palette, colored);
+    let mut stdout = io::stdout();
+    let mut stdin = io::stdin();
+    let mut terminal = RawTerminal::enter()?;
+    loop {
+        browser.render(&mut stdout, window_size().unwrap_or(DEFAULT_SIZE))?;
+        let mut buffer = [0; 64];
+        let read = stdin.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        match Key::parse(&buffer[..read]) {
+            Key::Quit => break,
+            Key::Enter => {
+                let Some(path) = browser.selected().map(path) else {
+                    continue;
+                };
+                // The terminal is restored while the shell runs and is entered again afterwards.
+                drop(terminal);
+                browser.message = open_shell(&path).err().map(|e| e.to_string());
+                terminal = RawTerminal::enter()?;
+            }
+            Key::CopyUrl => {
+                let (message, url) = match browser.selected() {
+                    Some(view) => match &view.url {
+                        Some(url) => (format!("copied url: {url}"), Some(url.clone())),
+                        None => (format!("no remote url for {}", view.name), None),
+                    },
+                    None => continue,
+                };
+                if let Some(url) = url {
+                    write!(stdout, "\x1b]52;c;{}\x07", encode_base64(url.as_bytes()))?;
+                }
+                browser.message = Some(message);
+            }
+            key => browser.handle(key),
+        }
+    }
+    drop(terminal);
+    Ok(())
+}
+
+/// The state of the interactive list.
+struct Browser {
+    views: Vec<RepositoryView>,
+    query: String,
+    status_filter: Option<Status>,
+    selected: usize,
+    offset: usize,
+    message: Option<String>,
+    palette: StatusPalette,
+    colored: bool,
+}
+
+impl Browser {
+    fn new(mut views: Vec<RepositoryView>, palette: StatusPalette, colored: bool) -> Self {
+        views.sort_by_key(path);
+        Self {
+            views,
+            query: String::new(),
+            status_filter: None,
+            selected: 0,
+            offset: 0,
+            message: None,
+            palette,
+            colored,
+        }
+    }
+
+    /// The views matching the search and the status filter.
+    fn visible(&self) -> Vec<&RepositoryView> {
+        self.views
+            .iter()
+            .filter(|view| matches(view, &self.query, self.status_filter))
+            .collect()
+    }
+
+    fn selected(&self) -> Option<&RepositoryView> {
+        self.visible().get(self.selected).copied()
+    }
+
+    /// Update the state for a key that does not need access to the terminal.
+    fn handle(&mut self, key: Key) {
+        let visible = self.visible().len();
+        let page = window_size()
+            .unwrap_or(DEFAULT_SIZE)
+            .0
+            .saturating_sub(2)
+            .max(1);
+        self.message = None;
+        match key {
+            Key::Up => self.selected = self.selected.saturating_sub(1),
+            Key::Down => self.selected = (self.selected + 1).min(visible.saturating_sub(1)),
+            Key::PageUp => self.selected = self.selected.saturating_sub(page),
+            Key::PageDown => self.selected = (self.selected + page).min(visible.saturating_sub(1)),
+            Key::Tab => {
+                // Cycle through every status in order of severity and then back to all of them.
+                let mut statuses = Status::value_variants().to_vec();
+                statuses.sort();
+                self.status_filter = match self.status_filter {
+                    None => statuses.first().copied(),
+                    Some(current) => statuses
+                        .iter()
+                        .skip_while(|status| **status != current)
+                        .nth(1)
+                        .copied(),
+                };
+                self.selected = 0;
+            }
+            Key::Backspace => {
+                self.query.pop();
+                self.selected = 0;
+            }
+            Key::Text(text) => {
+                self.query.push_str(&text);
+                self.selected = 0;
+            }
+            Key::Enter | Key::CopyUrl | Key::Quit | Key::Other => {}
+        }
+    }
+
+    /// Draw the header, the visible views (scrolled so that the selected view is shown) and the
+    /// footer, with every line truncated to the width of the terminal.
+    fn render(&mut self, out: &mut impl Write, (rows, columns): (usize, usize)) -> io::Result<()> {
+        let list_rows = rows.saturating_sub(2).max(1);
+        let selected = self.selected.min(self.visible().len().saturating_sub(1));
+        if selected < self.offset {
+            self.offset = selected;
+        } else if selected >= self.offset + list_rows {
+            self.offset = selected + 1 - list_rows;
+        }
+        let visible = self.visible();
+
+        let mut frame = String::from(CLEAR_SCREEN);
+        let header = format!(
+            "gfld: {}/{} repos | status: {} | search: {}",
+            visible.len(),
+            self.views.len(),
+            self.status_filter.map_or("all", |status| status.as_str()),
+            self.query
+        );
+        self.push_line(&mut frame, &[(&header, bold())], columns);
+
+        let width = |field: fn(&RepositoryView) -> &str| {
+            visible
+                .iter()
+                .map(|view| field(view).chars().count())
+                .max()
+                .unwrap_or_default()
+                .min(40)
+        };
+        let (name_width, branch_width) = (width(|view| &view.name), width(|view| &view.branch));
+        for (index, view) in visible.iter().enumerate().skip(self.offset).take(list_rows) {
+            let marker = match index == selected {
+                true => "> ",
+                false => "  ",
+            };
+            let name = format!("{marker}{:<name_width$}  ", view.name);
+            let status = format!("{:<10}  ", view.status.as_str());
+            let branch = format!("{:<branch_width$}  ", view.branch);
+            let full_path = path(view).display().to_string();
+            let segments = [
+                (
+                    name.as_str(),
+                    match index == selected {
+                        true => bold(),
+                        false => ColorSpec::new(),
+                    },
+                ),
+                (status.as_str(), self.palette.color_spec(view.status)),
+                (
+                    branch.as_str(),
+                    ColorSpec::new().set_fg(Some(Color::Cyan)).clone(),
+                ),
+                (full_path.as_str(), dimmed()),
+            ];
+            self.push_line(&mut frame, &segments, columns);
+        }
+        for _ in visible.len().saturating_sub(self.offset).min(list_rows)..list_rows {
+            frame.push_str("\r\n");
+        }
+
+        let footer = self.message.as_deref().unwrap_or(KEY_BINDINGS);
+        self.push_line(&mut frame, &[(footer, dimmed())], columns);
+        // The footer is the last line, so the trailing line break would scroll the screen.
+        let frame = frame.strip_suffix("\r\n").unwrap_or(&frame);
+        out.write_all(frame.as_bytes())?;
+        out.flush()
+    }
+
+    /// Append a line made of segments with their color specs (if colored), truncated to the width.
+    fn push_line(&self, frame: &mut String, segments: &[(&str, ColorSpec)], width: usize) {
+        let mut remaining = width;
+        for (text, color_spec) in segments {
+            let text: String = text.chars().take(remaining).collect();
+            remaining -= text.chars().count();
+            match self.colored && !color_spec.is_none() {
+                true => frame.push_str(&colorize(&text, color_spec)),
+                false => frame.push_str(&text),
+            }
+        }
+        frame.push_str("\r\n");
+    }
+}
+
+/// Check if the view's name contains the query (ignoring case) and its status matches the filter.
+fn matches(view: &RepositoryView, query: &str, status_filter: Option<Status>) -> bool {
+    status_filter.is_none_or(|status| view.status == status)
+        && view.name.to_lowercase().contains(&query.to_lowercase())
+}
+
+/// The full path to the repository for the view.
+fn path(view: &RepositoryView) -> PathBuf {
+    match &view.parent {
+        Some(parent) => Path::new(parent).join(&view.name),
+        None => PathBuf::from(&view.name),
+    }
+}
+
+fn bold() -> ColorSpec {
+    ColorSpec::new().set_bold(true).clone()
+}
+
+fn dimmed() -> ColorSpec {
+    ColorSpec::new().set_dimmed(true).clone()
+}
+
+/// Wrap the text in the ANSI escape codes for the color spec.
+fn colorize(text: &str, color_spec: &ColorSpec) -> String {
+    let mut ansi = Ansi::new(Vec::new());
+    // Writing to a vector cannot fail.
+    let _ = ansi
+        .set_color(color_spec)
+        .and_then(|()| ansi.write_all(text.as_bytes()))
+        .and_then(|()| ansi.reset());
+    String::from_utf8_lossy(&ansi.into_inner()).into_owned()
+}
+
+/// Run the user's shell (or "sh" if `$SHELL` is not set) in the directory until it exits.
+fn open_shell(path: &Path) -> Result<()> {
+    let shell = env::var_os("SHELL").unwrap_or_else(|| OsString::from("sh"));
+    println!("opening shell in {} (exit to return)", path.display());
+    debug!("opening shell ({shell:?}) in {}", path.display());
+    Command::new(shell).current_dir(path).status()?;
+    Ok(())
+}
+
+/// Encode the bytes as standard base64 with padding (as required by OSC 52).
+fn encode_base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| {
+            group | u32::from(*byte) << (16 - 8 * index)
+        });
+        for index in 0..4 {
+            match index <= chunk.len() {
+                true => encoded.push(char::from(
+                    ALPHABET[(group >> (18 - 6 * index)) as usize & 63],
+                )),
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
+
+/// A key press (or pasted text) read from the terminal in raw mode.
+#[remain::sorted]
+enum Key {
+    Backspace,
+    CopyUrl,
+    Down,
+    Enter,
+    Other,
+    PageDown,
+    PageUp,
+    Quit,
+    Tab,
+    Text(String),
+    Up,
+}
+
+impl Key {
+    /// Parse the bytes from a single read. Escape sequences for keys are expected to arrive in one
+    /// read, so a lone escape is a key press of its own.
+    fn parse(bytes: &[u8]) -> Self {
+        match bytes {
+            b"\x1b[A" | b"\x1bOA" | [0x10] => Self::Up,
+            b"\x1b[B" | b"\x1bOB" | [0x0e] => Self::Down,
+            b"\x1b[5~" => Self::PageUp,
+            b"\x1b[6~" => Self::PageDown,
+            b"\t" => Self::Tab,
+            [0x7f] | [0x08] => Self::Backspace,
+            b"\r" | b"\n" => Self::Enter,
+            [0x19] => Self::CopyUrl,
+            [0x1b] | [0x03] | [0x04] => Self::Quit,
+            _ => match std::str::from_utf8(bytes) {
+                Ok(text) if !text.chars().any(char::is_control) => Self::Text(text.to_string()),
+                _ => Self::Other,
+            },
+        }
+    }
+}
+
+/// Find the size of the terminal that `stdout` is attached to, in rows and columns.
+#[cfg(unix)]
+fn window_size() -> Option<(usize, usize)> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: "TIOCGWINSZ" only writes to the provided "winsize", which outlives the call.
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    (result == 0 && size.ws_row > 0 && size.ws_col > 0)
+        .then_some((usize::from(size.ws_row), usize::from(size.ws_col)))
+}
+
+#[cfg(not(unix))]
+fn window_size() -> Option<(usize, usize)> {
+    None
+}
+
+/// Switches to the alternate screen and puts the terminal attached to `stdin` in raw mode (i.e.
+/// input is read per key without echoing and signals are not generated) until dropped.
+#[cfg(unix)]
+struct RawTerminal {
+    original: libc::termios,
+}
+
+#[cfg(unix)]
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        // SAFETY: "termios" is plain data that "tcgetattr" fully initializes on success.
+        let mut original = unsafe { std::mem::zeroed::<libc::termios>() };
+        // SAFETY: the pointer is valid for the duration of the call.
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+        raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        // SAFETY: the pointer is valid for the duration of the call.
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut stdout = io::stdout();
+        write!(stdout, "{ENTER_ALTERNATE_SCREEN}")?;
+        stdout.flush()?;
+        Ok(Self { original })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        // The terminal is restored regardless of whether writing succeeds.
+        let _ = write!(stdout, "{LEAVE_ALTERNATE_SCREEN}").and_then(|()| stdout.flush());
+        // SAFETY: the pointer is valid for the duration of the call.
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original) };
+    }
+}
+
+/// Raw input is not supported on this platform, so the interactive list is never entered (see
+/// [`is_supported()`]).
+#[cfg(not(unix))]
+struct RawTerminal;
+
+#[cfg(not(unix))]
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "raw input is not supported on this platform",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn base64() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foo"), "Zm9v");
+        assert_eq!(
+            encode_base64(b"git@github.com:nickgerace/gfold.git"),
+            "Z2l0QGdpdGh1Yi5jb206bmlja2dlcmFjZS9nZm9sZC5naXQ="
+        );
+    }
+
+    #[allow(clippy::panic_in_result_fn)]
+    #[test]
+    fn search_and_filter() -> Result<()> {
+        let view = RepositoryView::finalize(
+            Path::new("/home/neloth/src/Gfold"),
+            Some("main".to_string()),
+            Status::Unpushed,
+            None,
+            None,
+            Vec::new(),
+        )?;
+        assert!(matches(&view, "", None));
+        assert!(matches(&view, "gfo", Some(Status::Unpushed)));
+        assert!(!matches(&view, "gfo", Some(Status::Clean)));
+        assert!(!matches(&view, "neloth", None));
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+//! This module contains the functionality for converting remote URLs between transports.
+
+use crate::args::UrlScheme;
+
+/// Convert the URL to the given scheme. SSH URLs are either in the "scp-like" form (e.g.
+/// "git@github.com:owner/repo.git") or use the "ssh://" scheme, in which case the port is dropped
+/// when converting to HTTPS. URLs to SSH always use the "git" user and the "scp-like" form. URLs
+/// that are already using the scheme or that cannot be converted (e.g. local paths) are returned
+/// as-is.
+pub(crate) fn convert(url: &str, scheme: UrlScheme) -> String {
+    match (scheme, split(url)) {
+        (UrlScheme::Https, Some((Transport::Ssh, host, path))) => format!("https://{host}/{path}"),
+        (UrlScheme::Ssh, Some((Transport::Https, host, path))) => format!("git@{host}:{path}"),
+        _ => url.to_string(),
+    }
+}
+
+enum Transport {
+    Https,
+    Ssh,
+}
+
+/// Split the URL into its transport, host (without the user or port) and path (without the
+/// leading slash). The value will be `None` if the URL is not a remote URL.
+fn split(url: &str) -> Option<(Transport, &str, &str)> {
+    let (transport, rest) = match url.split_once("://") {
+        Some(("https" | "http", rest)) => (Transport::Https, rest),
+        Some(("ssh" | "git+ssh", rest)) => (Transport::Ssh, rest),
+        Some(_) => return None,
+        // The "scp-like" form requires a colon before the first slash (otherwise it is a path) and
+        // a host that is not a single letter (otherwise it is a Windows drive).
+        None => {
+            let (authority, path) = url.split_once(':')?;
+            if authority.contains('/') || authority.len() < 2 {
+                return None;
+            }
+            let host = authority
+                .rsplit_once('@')
+                .map_or(authority, |(_, host)| host);
+            return Some((Transport::Ssh, host, path.trim_start_matches('/')));
+        }
+    };
+    let (authority, path) = rest.split_once('/')?;
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = host.split_once(':').map_or(host, |(host, _)| host);
+    Some((transport, host, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn convert_urls() {
+        let https = "https://github.com/nickgerace/gfold.git";
+        let ssh = "git@github.com:nickgerace/gfold.git";
+        assert_eq!(convert(ssh, UrlScheme::Https), https);
+        assert_eq!(convert(https, UrlScheme::Ssh), ssh);
+        assert_eq!(convert(https, UrlScheme::Https), https);
+        assert_eq!(convert(ssh, UrlScheme::Ssh), ssh);
+        assert_eq!(
+            convert("ssh://git@example.com:2222/team/repo.git", UrlScheme::Https),
+            "https://example.com/team/repo.git"
+        );
+        assert_eq!(
+            convert("https://token@example.com/team/repo", UrlScheme::Ssh),
+            "git@example.com:team/repo"
+        );
+        assert_eq!(
+            convert("/srv/git/repo.git", UrlScheme::Https),
+            "/srv/git/repo.git"
+        );
+        assert_eq!(
+            convert("file:///srv/repo", UrlScheme::Ssh),
+            "file:///srv/repo"
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! This module contains the functionality for re-running collection and display on an interval.
+
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use log::debug;
+
+/// Switches to the alternate screen, which leaves the original screen untouched while watching.
+const ENTER_ALTERNATE_SCREEN: &str = "\x1b[?1049h";
+/// Resets colors and switches back to the original screen.
+const LEAVE_ALTERNATE_SCREEN: &str = "\x1b[0m\x1b[?1049l";
+/// Clears the screen and moves the cursor to the top-left corner.
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// Run the iteration on the given interval until interrupted or until an iteration fails (i.e. this
+/// function only returns with the iteration's error). Each iteration is rendered on a cleared
+/// alternate screen and the original screen is restored afterwards, including when interrupted
+/// with Ctrl-C (or when terminated).
+pub(crate) fn run(interval: Duration, mut iteration: impl FnMut() -> Result<()>) -> Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "{ENTER_ALTERNATE_SCREEN}")?;
+    interrupt::restore_terminal_on_exit();
+
+    let error = loop {
+        if let Err(e) = write!(stdout, "{CLEAR_SCREEN}")
+            .map_err(Into::into)
+            .and_then(|()| iteration())
+            .and_then(|()| Ok(stdout.flush()?))
+        {
+            break e;
+        }
+        debug!("watching; next iteration in {}s", interval.as_secs());
+        thread::sleep(interval);
+    };
+
+    write!(stdout, "{LEAVE_ALTERNATE_SCREEN}")?;
+    stdout.flush()?;
+    Err(error)
+}
+
+#[cfg(unix)]
+mod interrupt {
+    use super::LEAVE_ALTERNATE_SCREEN;
+
+    /// The conventional exit code for a process interrupted by "SIGINT" (also used for "SIGTERM").
+    const EXIT_CODE_INTERRUPTED: i32 = 130;
+
+    /// Install handlers for "SIGINT" and "SIGTERM" that restore the terminal and exit.
+    pub(super) fn restore_terminal_on_exit() {
+        let handler = handle as extern "C" fn(libc::c_int) as *const () as libc::sighandler_t;
+        // SAFETY: the handler only calls async-signal-safe functions.
+        unsafe {
+            libc::signal(libc::SIGINT, handler);
+            libc::signal(libc::SIGTERM, handler);
+        }
+    }
+
+    extern "C" fn handle(_signal: libc::c_int) {
+        // SAFETY: "write" and "_exit" are async-signal-safe and the buffer outlives the call. The
+        // result of "write" is ignored since we are exiting regardless.
+        unsafe {
+            libc::write(
+                libc::STDOUT_FILENO,
+                LEAVE_ALTERNATE_SCREEN.as_ptr().cast(),
+                LEAVE_ALTERNATE_SCREEN.len(),
+            );
+            libc::_exit(EXIT_CODE_INTERRUPTED);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod interrupt {
+    /// Signal handlers are only installed on Unix-like platforms. Elsewhere, the terminal is
+    /// responsible for restoring the original screen when interrupted.
+    pub(super) fn restore_terminal_on_exit() {}
+}